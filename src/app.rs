@@ -1,91 +1,464 @@
 #![allow(dead_code)]
 
+mod checksum;
+mod command;
+mod disk_usage;
+mod external;
+mod finder;
+mod fuzzy;
+mod icons;
+mod picker;
+mod preview;
+
 use std::borrow::Cow;
-use std::fs::metadata;
-use std::path::PathBuf;
+use std::fs::{metadata, FileType};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::os::unix::fs::FileTypeExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 use anyhow::Result;
-use std::io::Stdout;
-use crate::fs_info::file_system_info::{FileSys, StatusFlag};
-use crate::fs_info::file_info::FileInfo;
+use crate::fs_info::file_system_info::{self, Clipboard, Conflict, FileSys, SortMode, StatusFlag};
+use crate::fs_info::file_info::{self, FileInfo};
+use crate::fs_info::owner::{self, OwnerNames};
+use crate::fs_info::git_status::GitState;
+use crate::tui::{self, Tui};
+use crate::config::{expand_home, Config, EscAction, OpenWithApp, TimeFormat};
+use crate::keys::{Action, Keymap};
+use crate::theme::Theme;
+use crate::state::{self, Session};
+use checksum::Checksum;
+use command::Command;
+use disk_usage::DiskUsage;
+use external::{Captured, External};
+use finder::{Finder, MatchMode};
+use picker::{Picker, PickerKind};
+use preview::Preview;
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use globset::Glob;
 use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph, Table, Row, Cell, TableState},
-    Frame, Terminal,
+    Frame,
 };
+use signal_hook::consts::SIGCONT;
+
+static TICK_RATE: Duration = Duration::from_millis(250);
+static MAX_NAV_HISTORY: usize = 64;
+static MAX_RECENT_FILES: usize = 32;
+static RECENT_FILES: &str = "recent_files";
+static MAX_RECENT_DIRS: usize = 32;
+static RECENT_DIRS: &str = "recent_dirs";
+static BOOKMARKS: &str = "bookmarks";
+static OWNER_WIDTH: u16 = 10;
+static MIN_WIDTH: u16 = 40;
+static MIN_HEIGHT: u16 = 10;
+static PREVIEW_SCROLL_STEP: u16 = 4;
+static MAX_COUNT: usize = 99_999;
+static PERMISSIONS_WIDTH: u16 = 10;
+static MODIFIED_WIDTH: u16 = 16; // "YYYY-MM-DD HH:MM"
+static MOUSE_SCROLL_ROWS: i32 = 3;
+static ARCHIVES: &[&str] = &["zip", "tar", "gz", "tgz", "xz", "bz2", "zst", "7z", "rar", "deb", "rpm"];
+static DOUBLE_CLICK: Duration = Duration::from_millis(400);
+static DISK_SPACE_TTL: Duration = Duration::from_secs(5);
 
 #[derive(PartialEq, Clone, Copy)]
 enum InputContext {
     None,
     NewFile,
     NewDir,
+    NewDirEnter, // create the dir, then cd into it
     Rename,
+    BatchRename, // pattern applied to all marked entries
     ConfirmDelete,
+    ConfirmEmptyDir, // typed "yes", it wipes the whole dir
+    ConfirmQuit,     // something would be lost by leaving now
+    PasteConflict,   // single key: overwrite, rename, skip
+    FlattenConflict, // same keys, for entries moved up out of `flatten_dir`
     Search,
+    RecursiveSearch, // whole subtree, results in a popup
+    GoTo,
+    Shell, // "!cmd", "%" is the file under the cursor
+    Command, // ":sort size", ":mkdir name", any action name
+    OwnerFilter,
+    MarkGlob, // "*.png", adds to the marks
+    ExtFilter, // "rs", only files with that extension
+    ArchiveName, // the marked entries are packed into it
+}
+
+// the pane not under the keyboard in dual-pane view, swapped in when it gets the focus
+struct Pane {
+    fs: FileSys,
+    table_state: TableState,
+    search_query: String,
+    nav_back: Vec<PathBuf>,
+    nav_forward: Vec<PathBuf>,
+    finder: Option<Finder>,
+    found_seen: usize,
+}
+
+// free space of the listed dir's filesystem, not asked every frame
+struct DiskSpace {
+    dir: PathBuf,
+    checked: Instant,
+    space: Option<(u64, u64)>, // (free, total)
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum TypeFilter {
+    All,
+    DirsOnly,
+    FilesOnly,
 }
 
 pub struct App {
+    config: Config,
+    keymap: Keymap,
+    theme: Theme,
     fs: FileSys,
     table_state: TableState, // cursor index
     input_context: InputContext,
     input_buffer: String,
+    confirm_message: String, // prompt text worked out when the confirmation started
+    confirm_size: Option<DiskUsage>, // fills the "{size}" of the prompt once it is counted
+    picker: Option<Picker>,
+    finder: Option<Finder>, // subtree search still filling the listing, dropping it stops the walk
+    found_seen: usize, // results of it already in the listing
+    checksum: Option<Checksum>, // behind the checksum popup, dropping it stops the hashing
+    captured: Option<Captured>, // shell command running in the background, summed up when it is done
     show_hidden: bool,
+    exact_sizes: bool,
+    show_owner: bool,
+    show_permissions: bool, // "drwxr-xr-x" column
+    show_modified: bool,
+    owner_filter: Option<u32>, // only list entries owned by this uid
+    ext_filter: Option<String>, // only list files with this (lowercase) extension
+    type_filter: TypeFilter,
+    owner_names: OwnerNames,
+    show_preview: bool,
+    hex_preview: bool,          // force hex even for text files
+    preview: Option<Preview>,   // cached head of the previewed file
+    preview_scroll: u16,        // first shown line (text) or row (hex)
+    page_rows: u16,             // data rows the table had room for on the last draw
+    table_area: Rect,           // where the active table was drawn, for mouse clicks
+    other_area: Option<Rect>,   // and the other pane, if there is one
+    row_heights: Vec<u16>,      // lines each listed row took, more than one with wrap_names
+    last_click: Option<(Instant, usize)>, // (when, row) of the previous left click
+    disk_space: Option<DiskSpace>,
     search_query: String,
+    search_before: (String, Option<usize>), // (query, cursor) to go back to when Esc drops a search
+    case_sensitive: bool,
+    fuzzy_search: bool, // subsequence match ranked by score, otherwise plain substring
+    search_filters: bool, // false keeps the whole listing and underlines the matches instead
+    nav_back: Vec<PathBuf>,    // dirs left behind, newest last
+    nav_forward: Vec<PathBuf>, // dirs stepped back from
+    recent_files: VecDeque<PathBuf>,    // opened files, newest first
+    recent_dirs: VecDeque<PathBuf>,     // visited dirs, newest first, saved on quit
+    bookmarks: Vec<PathBuf>,            // in the order they were added
+    pending_external: Option<External>, // run by the main loop, it owns the terminal
+    queued_ids: Vec<u64>, // job ids behind the rows of the queue view
+    count: Option<usize>, // digits typed ahead of a motion
+    other_pane: Option<Pane>,
+    active_left: bool,              // which side the active pane is drawn on
+    paste_target: Option<PathBuf>,  // the other pane's dir while its paste conflicts are asked about
+    paste_link: bool,               // the paste being asked about makes symlinks
+    flatten_dir: Option<PathBuf>,   // the dir whose conflicts are asked about
+    saved_clipboard: Option<Clipboard>, // what the user had copied before a pane transfer borrowed the clipboard
     should_quit: bool,
 }
 
 impl App {
-    pub fn new(start_dir: PathBuf) -> Result<App> {
-        let app = App{
-            fs: FileSys::init(start_dir)?,
+    pub fn new(start_dir: PathBuf, config: Config, keymap: Keymap, theme: Theme) -> Result<App> {
+        let session = state::load_session();
+        let mut fs = FileSys::init(start_dir, &config)?;
+        fs.restore_sort(session.sort_mode, session.sort_reversed);
+        let mut app = App{
+            fs,
             table_state: TableState::default(),
             input_context: InputContext::None,
             input_buffer: String::new(),
-            show_hidden: false,
+            confirm_message: String::new(),
+            confirm_size: None,
+            picker: None,
+            finder: None,
+            found_seen: 0,
+            checksum: None,
+            captured: None,
+            show_hidden: session.show_hidden,
+            exact_sizes: false,
+            show_owner: false,
+            show_permissions: false,
+            show_modified: false,
+            owner_filter: None,
+            ext_filter: None,
+            type_filter: TypeFilter::All,
+            owner_names: OwnerNames::load(),
+            show_preview: session.show_preview,
+            hex_preview: false,
+            preview: None,
+            preview_scroll: 0,
+            page_rows: 0,
+            table_area: Rect::default(),
+            other_area: None,
+            row_heights: Vec::new(),
+            last_click: None,
+            disk_space: None,
             search_query: String::new(),
+            search_before: (String::new(), None),
+            case_sensitive: config.case_sensitive_search,
+            fuzzy_search: config.fuzzy_search,
+            search_filters: config.search_filters,
+            nav_back: Vec::new(),
+            nav_forward: Vec::new(),
+            recent_files: state::load_paths(RECENT_FILES)
+                .into_iter()
+                .filter(|path| path.exists())
+                .take(MAX_RECENT_FILES)
+                .collect(),
+            recent_dirs: state::load_paths(RECENT_DIRS)
+                .into_iter()
+                .filter(|dir| dir.is_dir())
+                .take(MAX_RECENT_DIRS)
+                .collect(),
+            bookmarks: state::load_paths(BOOKMARKS),
+            pending_external: None,
+            queued_ids: Vec::new(),
+            count: None,
+            other_pane: None,
+            active_left: true,
+            paste_target: None,
+            paste_link: false,
+            flatten_dir: None,
+            saved_clipboard: None,
             should_quit: false,
+            keymap,
+            theme,
+            config, // last, the fields above read from it
         };
+        app.remember_recent_dir();
         Ok(app)
     }
 
-    pub fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    pub fn run(&mut self, terminal: &mut Tui) -> Result<()> {
+        // set by SIGCONT, i.e. after `fg` brought us back from a suspend
+        let resumed = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(SIGCONT, Arc::clone(&resumed))?;
+
         loop {
+            if resumed.swap(false, Ordering::Relaxed) {
+                tui::resume_terminal(terminal)?;
+            }
+            // the worker finished something, the listing has changed under us
+            let finished = self.preserving_cursor(|app| app.fs.poll_jobs()).unwrap_or(false);
+            // a big dir still coming in
+            let _ = self.preserving_cursor(|app| Ok(app.fs.poll_loading()));
+            if self.fs.changed_on_disk() {
+                let _ = self.refresh_in_place();
+            }
+            // same for the other pane; a finished job may have copied into either one
+            let other_finished = self.with_other_pane(|app| {
+                let done = app.preserving_cursor(|app| app.fs.poll_jobs()).unwrap_or(false);
+                let _ = app.preserving_cursor(|app| Ok(app.fs.poll_loading()));
+                if finished || app.fs.changed_on_disk() {
+                    let _ = app.refresh_in_place();
+                }
+                done
+            });
+            if other_finished == Some(true) {
+                let _ = self.refresh_in_place();
+            }
+            self.update_finder();
+            self.update_checksum();
+            self.update_captured();
             terminal.draw(|frame| self.ui(frame))?;
 
             if self.should_quit {
+                self.save_session();
                 return Ok(())
             }
-            if let Ok(Event::Key(key)) = event::read() {
-                if key.kind == KeyEventKind::Press {
-                    let _ = self.handle_key(key.code);
+            // poll instead of blocking read, so a resume is noticed without a key press
+            if !event::poll(TICK_RATE)? {
+                continue;
+            }
+            match event::read() {
+                Ok(Event::Key(key)) => {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                        tui::suspend_terminal(terminal)?;
+                    } else {
+                        let _ = self.handle_key(key);
+                    }
+                    if let Some(external) = self.pending_external.take() {
+                        tui::restore_terminal(terminal)?;
+                        let res = self.run_external(external);
+                        tui::resume_terminal(terminal)?;
+                        if let Err(error) = res {
+                            self.fs.status_info = format!("Error: {}", error);
+                            self.fs.status_flag = StatusFlag::Error;
+                        }
+                    }
+                }
+                Ok(Event::Mouse(mouse)) => {
+                    let _ = self.handle_mouse(mouse);
+                }
+                // the next draw checks the new size against the minimum
+                Ok(Event::Resize(_, _)) => terminal.autoresize()?,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn current_dir(&self) -> &PathBuf {
+        self.fs.current_dir()
+    }
+
+    // cursor onto `name`, e.g. a file given on the command line
+    pub fn select(&mut self, name: &str) {
+        self.focus_file(name);
+    }
+
+    // a failed save only costs the next start its settings
+    fn save_session(&self) {
+        let _ = state::save_session(&Session {
+            show_hidden: self.show_hidden,
+            sort_mode: self.fs.sort_mode(),
+            sort_reversed: self.fs.sort_reversed(),
+            show_preview: self.show_preview,
+            last_dir: Some(self.fs.current_dir().clone()),
+        });
+        let _ = state::save_paths(RECENT_DIRS, &self.recent_dirs);
+    }
+
+    fn run_external(&mut self, external: External) -> Result<()> {
+        match external {
+            External::FuzzyPicker => self.pick_with_fuzzy_finder(),
+            External::Editor(path) => self.edit_file(path),
+            External::Shell(command) => {
+                let code = external::run_interactive(&command)?;
+                self.preserving_cursor(|app| app.fs.refresh())?;
+                self.fs.status_info = match code {
+                    Some(code) => format!("exit code {}", code),
+                    None => "killed by signal".to_string(),
+                };
+                self.fs.status_flag = if code == Some(0) { StatusFlag::Others } else { StatusFlag::Error };
+                Ok(())
+            }
+            External::Subshell(dir) => {
+                let code = external::open_shell(&dir)?;
+                // anything may have changed in there
+                self.preserving_cursor(|app| app.fs.refresh())?;
+                if let Some(code) = code.filter(|code| *code != 0) {
+                    self.fs.status_info = format!("Shell exited with {}", code);
+                    self.fs.status_flag = StatusFlag::Error;
                 }
+                Ok(())
+            }
+            External::OpenWith(command, path) => {
+                external::run_attached(&command, &path)?;
+                self.preserving_cursor(|app| app.fs.refresh())?;
+                Ok(())
             }
         }
     }
 
-    fn handle_key(&mut self, key: KeyCode) -> Result<()> {
-        if self.input_context != InputContext::None {
-            self.handle_input_mode(key)
+    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.picker.is_some() {
+            self.handle_picker_mode(key.code)
+        } else if self.input_context != InputContext::None {
+            self.handle_input_mode(key.code)
         } else {
             self.handle_normal_mode(key)
         }
     }
 
     ///
-    /// # Key Handler in Input Mod
+    /// # Mouse
     ///
+    // only the listing takes clicks, prompts and popups are keyboard only
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        if self.picker.is_some() || self.input_context != InputContext::None {
+            return Ok(());
+        }
+        let inside = |area: Rect| area.contains((mouse.column, mouse.row).into());
+        match mouse.kind {
+            // clamped, a wheel flick should not wrap around to the other end
+            MouseEventKind::ScrollDown if inside(self.table_area) => self.move_cursor(MOUSE_SCROLL_ROWS, true),
+            MouseEventKind::ScrollUp if inside(self.table_area) => self.move_cursor(-MOUSE_SCROLL_ROWS, true),
+            MouseEventKind::Down(MouseButton::Left) if inside(self.table_area) => self.click_row(mouse.row),
+            // a click on the other pane gives it the focus
+            MouseEventKind::Down(MouseButton::Left) if self.other_area.is_some_and(inside) => self.switch_pane(),
+            _ => Ok(()),
+        }
+    }
+
+    fn click_row(&mut self, y: u16) -> Result<()> {
+        // top border and header come before the first row
+        let mut line = match y.checked_sub(self.table_area.y + 2) {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let mut row = self.table_state.offset();
+        loop {
+            match self.row_heights.get(row) {
+                Some(&height) if line < height => break,
+                Some(&height) => line -= height,
+                None => return Ok(()), // below the last row
+            }
+            row += 1;
+        }
+
+        let double = self.last_click.is_some_and(|(at, last)| last == row && at.elapsed() < DOUBLE_CLICK);
+        self.table_state.select(Some(row));
+        if double {
+            self.last_click = None;
+            if let Some((_, true)) = self.get_cursor_file_info() {
+                return self.enter_current();
+            }
+        } else {
+            self.last_click = Some((Instant::now(), row));
+        }
+        Ok(())
+    }
 
+    ///
+    /// # Key Handler in Input Mod
+    ///
     fn handle_input_mode(&mut self, key: KeyCode) -> Result<()> {
+        if self.input_context == InputContext::PasteConflict {
+            return self.answer_paste_conflict(key);
+        }
+        if self.input_context == InputContext::FlattenConflict {
+            return self.answer_flatten_conflict(key);
+        }
         match key {
             KeyCode::Char(c) => self.input_buffer.push(c),
             KeyCode::Backspace => {self.input_buffer.pop();},
-            KeyCode::Enter => self.submit_input()?,
-            KeyCode::Esc => self.exit_input_mode(),
-            _ => {}
+            KeyCode::Enter => return self.submit_input(),
+            KeyCode::Esc if self.input_context == InputContext::Search => {
+                // back to the list as it was before "/"
+                let (query, cursor) = std::mem::take(&mut self.search_before);
+                self.search_query = query;
+                self.table_state.select(cursor);
+                self.clamp_cursor();
+                self.exit_input_mode();
+                return Ok(());
+            }
+            KeyCode::Esc => {
+                self.exit_input_mode();
+                return Ok(());
+            }
+            _ => return Ok(()),
+        }
+        // the listing narrows while typing
+        if self.input_context == InputContext::Search {
+            self.search_query = self.input_buffer.trim().to_string();
+            self.cursor_to_search();
         }
 
         Ok(())
@@ -96,15 +469,89 @@ impl App {
 
         if self.input_context == InputContext::Search {
             self.search_query = input;
-            self.reset_cursor();
+            self.cursor_to_search();
             self.clear_selection();
             self.exit_input_mode();
             return Ok(());
         }
+        if self.input_context == InputContext::RecursiveSearch {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.start_finder(input);
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::Shell {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.run_shell(&input)?;
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::Command {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.run_command(&input)?;
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::GoTo {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.go_to_path(&input)?;
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::MarkGlob {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.mark_matching(&input);
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::ArchiveName {
+            self.exit_input_mode();
+            if !input.is_empty() {
+                self.create_archive(&input)?;
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::ExtFilter {
+            self.exit_input_mode();
+            self.set_ext_filter(&input);
+            return Ok(());
+        }
+        if self.input_context == InputContext::OwnerFilter {
+            self.exit_input_mode();
+            self.set_owner_filter(&input);
+            return Ok(());
+        }
+        if self.input_context == InputContext::ConfirmEmptyDir {
+            if input == "yes" {
+                self.close_input();
+                self.fs.empty_current_dir()?;
+                self.reset_cursor();
+            } else {
+                self.exit_input_mode();
+            }
+            return Ok(());
+        }
+        if self.input_context == InputContext::ConfirmQuit {
+            if input == "y" || input == "Y" {
+                self.close_input();
+                self.should_quit = true;
+            } else if input == "n" || input == "N" {
+                self.exit_input_mode();
+            }
+            return Ok(());
+        }
         if self.input_context == InputContext::ConfirmDelete {
             if input == 'y'.to_string() || input == 'Y'.to_string() {
-                self.fs.delete_selected()?;
-                self.exit_input_mode();
+                self.close_input();
+                // looked up again, the listing may have changed while the prompt was up
+                let (indices, hidden) = self.listed_marks();
+                self.preserving_cursor(|app| app.fs.delete_entries(&indices))?;
+                self.note_hidden_marks(hidden);
             } else if input == 'n'.to_string() || input == 'N'.to_string() {
                 self.exit_input_mode();
             }
@@ -112,77 +559,322 @@ impl App {
             return Ok(());
         }
 
-        if !input.is_empty() {
-            let result = match self.input_context {
-                InputContext::NewFile => self.fs.new_file(&input, false),
-                InputContext::NewDir => self.fs.new_file(&input, true),
-                InputContext::Rename => self.fs.rename_selected(&input),
-                _ => Ok(())
-            };
+        if input.is_empty() {
+            self.exit_input_mode();
+            return Ok(());
+        }
+
+        let context = self.input_context;
+        self.close_input();
+
+        // the name the cursor should land on afterwards
+        let mut focus = input.as_str();
+        let result = match context {
+            InputContext::NewFile => {
+                // "a.txt b.txt c.txt" (or with commas) creates all three as one undo step
+                let names: Vec<&str> = input
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                match names.as_slice() {
+                    [] => Ok(()),
+                    [name] => {
+                        focus = name;
+                        self.fs.new_file(name, false)
+                    }
+                    _ => {
+                        focus = names[0];
+                        self.fs.new_files(&names)
+                    }
+                }
+            }
+            InputContext::NewDir => {
+                // "a/b/c" made all three, the top one is what is listed here
+                focus = input.split('/').find(|component| !component.is_empty()).unwrap_or(&input);
+                self.fs.new_file(&input, true)
+            }
+            InputContext::NewDirEnter => self.new_dir_and_enter(&input),
+            InputContext::Rename => match self.get_cursor_file_info() {
+                Some((original_index, _)) => self.fs.rename_entry(original_index, &input),
+                None => Ok(()),
+            },
+            InputContext::BatchRename => {
+                let (indices, hidden) = self.listed_marks();
+                let renamed = self.preserving_cursor(|app| app.fs.batch_rename(&indices, &input));
+                self.note_hidden_marks(hidden);
+                renamed
+            }
+            _ => Ok(())
+        };
 
-            if let Err(error) = result {
+        match result {
+            Ok(()) => {
+                self.focus_file(focus);
+            }
+            Err(error) => {
                 self.fs.status_info = format!("Error: {}", error);
                 self.fs.status_flag = StatusFlag::Error;
             }
         }
-
-        self.exit_input_mode();
         Ok(())
     }
 
     // clear input buffer and flags
     fn exit_input_mode(&mut self) {
-        self.input_context = InputContext::None;
-        self.input_buffer.clear();
+        self.close_input();
         self.fs.status_info = "Ready".to_string();
         self.fs.status_flag = StatusFlag::Ready;
     }
 
+    // leave input mode but keep the status the submitted operation reported
+    fn close_input(&mut self) {
+        self.input_context = InputContext::None;
+        self.input_buffer.clear();
+        self.confirm_size = None;
+    }
+
     ///
-    /// # Key Handler in Normal Mode
+    /// # Key Handler in Picker Popup
     ///
-    fn handle_normal_mode(&mut self, key: KeyCode) -> Result<()> {
+    fn handle_picker_mode(&mut self, key: KeyCode) -> Result<()> {
         match key {
+            KeyCode::Char('j') | KeyCode::Down => self.move_picker_cursor(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_picker_cursor(-1),
+            KeyCode::Enter | KeyCode::Char('l') => {
+                if let Some(picker) = self.picker.take() {
+                    self.submit_picker(picker)?;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.close_picker(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn close_picker(&mut self) {
+        self.checksum = None;
+        self.picker = None;
+    }
+
+    fn move_picker_cursor(&mut self, delta: i32) {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.move_cursor(delta);
+        }
+    }
+
+    fn submit_picker(&mut self, picker: Picker) -> Result<()> {
+        let index = match picker.selected() {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        match picker.kind {
+            PickerKind::Clipboard => {
+                if self.fs.use_from_history(index) {
+                    self.start_paste(None)?;
+                }
+            }
+            PickerKind::OpenWith => {
+                if let (Some(app), Some((original_index, false))) = (self.config.open_with.get(index).cloned(), self.get_cursor_file_info()) {
+                    let path = self.fs.files()[original_index].path.clone();
+                    self.open_with_app(path, app);
+                }
+            }
+            PickerKind::Queue => {
+                if let Some(id) = self.queued_ids.get(index) {
+                    self.fs.cancel_job(*id);
+                }
+            }
+            PickerKind::Bookmarks => {
+                if let Some(dir) = self.bookmarks.get(index).cloned() {
+                    self.jump_to_dir(dir)?;
+                }
+            }
+            PickerKind::RecentDirs => {
+                let skipped = usize::from(self.recent_dirs.front() == Some(self.fs.current_dir()));
+                if let Some(dir) = self.recent_dirs.get(index + skipped).cloned() {
+                    self.jump_to_dir(dir)?;
+                }
+            }
+            PickerKind::RecentFiles => {
+                if let Some(path) = self.recent_files.get(index) {
+                    self.pending_external = Some(External::Editor(path.clone()));
+                }
+            }
+            PickerKind::Checksum => {
+                if let Some(Ok((sha256, md5))) = self.checksum.take().and_then(|checksum| checksum.sums()) {
+                    self.copy_text(if index == 0 { sha256 } else { md5 });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// # Key Handler in Normal Mode
+    ///
+    fn handle_normal_mode(&mut self, key: KeyEvent) -> Result<()> {
+        // vim-like count in front of a motion, "5j"; a leading 0 is no count
+        if let (KeyCode::Char(digit @ '0'..='9'), false) = (key.code, self.keymap.is_pending()) {
+            if key.modifiers.is_empty() && (digit != '0' || self.count.is_some()) {
+                let count = self.count.unwrap_or(0) * 10 + digit.to_digit(10).unwrap() as usize;
+                self.count = Some(count.min(MAX_COUNT));
+                return Ok(());
+            }
+        }
+        let action = match self.keymap.action(key) {
+            Some(action) => action,
+            None => {
+                // an open sequence ("1" "0" "g" ...) keeps its count
+                if !self.keymap.is_pending() {
+                    self.count = None;
+                }
+                return Ok(());
+            }
+        };
+        // only motions use it, anything else just drops it
+        let count = self.count.take();
+        self.run_action(action, count)
+    }
+
+    // keys and ":" commands end up here alike
+    fn run_action(&mut self, action: Action, count: Option<usize>) -> Result<()> {
+        let times = count.unwrap_or(1) as i32;
+        match action {
             // guide
-            KeyCode::Char('j') => self.move_cursor(-1),
-            KeyCode::Char('k') => self.move_cursor(1),
-            KeyCode::Char('h') => self.go_parent_dir(),
-            KeyCode::Char('l') => self.enter_current(),
+            // a positive delta moves the highlight down the table
+            Action::Down => self.move_cursor(times, times > 1),
+            Action::Up => self.move_cursor(-times, times > 1),
+            Action::Top | Action::Bottom if count.is_some() => self.cursor_to_row(count.unwrap_or(1)),
+            Action::Top => self.jump_cursor(false),
+            Action::Bottom => self.jump_cursor(true),
+            Action::PageDown => self.move_page(times as f32),
+            Action::PageUp => self.move_page(-times as f32),
+            Action::HalfPageDown => self.move_page(0.5 * times as f32),
+            Action::HalfPageUp => self.move_page(-0.5 * times as f32),
+            Action::Parent => self.go_parent_dir(),
+            Action::Enter => self.enter_current(),
+            Action::FollowSymlink => self.follow_symlink(),
+            // browser-like history, kept apart from undo
+            Action::Back => self.go_back(),
+            Action::Forward => self.go_forward(),
+            Action::GoTo => {
+                self.input_context = InputContext::GoTo;
+                self.input_buffer.clear();
+                Ok(())
+            }
+            Action::RecentDirs => self.open_recent_dirs_picker(),
+            Action::ToggleBookmark => self.toggle_bookmark(),
+            Action::Bookmarks => self.open_bookmarks_picker(),
 
             // selection
-            KeyCode::Char(' ') => self.toggle_selection(),
+            Action::ToggleMark => self.toggle_selection(),
+            Action::MarkAll => self.mark_listed(false),
+            Action::UnmarkAll => self.unmark_all(),
+            Action::InvertMarks => self.mark_listed(true),
+            Action::MarkGlob => {
+                self.input_context = InputContext::MarkGlob;
+                self.input_buffer.clear();
+                Ok(())
+            }
+            Action::CopyRelativePath => self.copy_relative_path(),
+            Action::CopyPath => self.copy_cursor_path(false),
+            Action::CopyName => self.copy_cursor_path(true),
+            Action::Checksum => self.start_checksum(),
 
             // file operations
-            KeyCode::Char('c') => self.fs.copy_selected(true),
-            KeyCode::Char('x') => self.fs.copy_selected(false),
-            KeyCode::Char('v') => self.fs.paste(),
-            KeyCode::Char('d') => self.start_delete_confirm(),
-            KeyCode::Char('u') => self.fs.undo(),
-            KeyCode::Char('r') => self.start_rename(),
+            Action::Copy => self.copy_current(true),
+            Action::Cut => self.copy_current(false),
+            Action::Paste => self.start_paste(None),
+            Action::PasteLink => self.start_link_paste(),
+            Action::PasteFromHistory => self.open_clipboard_picker(),
+            Action::Delete => self.start_delete_confirm(),
+            Action::EmptyDir => self.start_empty_dir_confirm(),
+            Action::Undo => self.preserving_cursor(|app| app.fs.undo()),
+            Action::Redo => self.preserving_cursor(|app| app.fs.redo()),
+            Action::Rename => self.start_rename(),
+            Action::Edit => self.edit_current(),
+            Action::RecentFiles => self.open_recent_files_picker(),
+            Action::Shell => {
+                self.input_context = InputContext::Shell;
+                self.input_buffer.clear();
+                Ok(())
+            }
+            Action::Subshell => {
+                self.pending_external = Some(External::Subshell(self.fs.current_dir().clone()));
+                Ok(())
+            }
+            Action::Command => {
+                self.input_context = InputContext::Command;
+                self.input_buffer.clear();
+                Ok(())
+            }
+            Action::OpenWith => self.open_with_picker(),
+            Action::OpenDefault => self.open_with_default(),
+            Action::Flatten => self.flatten_current(),
+            Action::Duplicate => self.duplicate_current(),
+            Action::ToggleExecutable => self.toggle_executable(),
+            Action::Touch => self.touch_current(),
+            Action::Extract => self.extract_current(),
+            Action::CreateArchive => self.start_create_archive(),
+            Action::CopyToPane => self.transfer_to_other_pane(true),
+            Action::MoveToPane => self.transfer_to_other_pane(false),
 
             // create
-            KeyCode::Char('n') => self.start_new_file(),
-            KeyCode::Char('m') => self.start_new_dir(),
+            Action::NewFile => self.start_new_file(),
+            Action::NewDir => self.start_new_dir(),
+            Action::NewDirEnter => self.start_new_dir_enter(),
 
-            // filter or search
-            KeyCode::Char('.') => self.toggle_hidden_files(),
-            KeyCode::Char('/') => self.start_search(),
-            KeyCode::Esc => self.clear_search(),
+            // view
+            Action::ToggleExactSizes => self.toggle_exact_sizes(),
+            Action::CycleSort => self.cycle_sort(),
+            Action::ReverseSort => self.toggle_sort_order(),
+            Action::ToggleOwner => self.toggle_owner_columns(),
+            Action::TogglePreview => self.toggle_preview(),
+            Action::ToggleHex => self.toggle_hex_preview(),
+            Action::ScrollPreviewDown => self.scroll_preview(PREVIEW_SCROLL_STEP as i32),
+            Action::ScrollPreviewUp => self.scroll_preview(-(PREVIEW_SCROLL_STEP as i32)),
+            Action::ToggleGit => self.toggle_git_status(),
+            Action::TogglePermissions => self.toggle_permissions_column(),
+            Action::ToggleModified => self.toggle_modified_column(),
+            Action::SwitchPane => self.switch_pane(),
+            Action::TogglePanes => self.toggle_dual_pane(),
 
-            // exit
-            KeyCode::Char('q') => {
-                self.should_quit = true;
+            // filter or search
+            Action::ToggleHidden => self.toggle_hidden_files(),
+            Action::ToggleIgnored => self.toggle_ignored_files(),
+            Action::CycleTypeFilter => self.cycle_type_filter(),
+            Action::Search => self.start_search(),
+            Action::SearchSubtree => {
+                self.input_context = InputContext::RecursiveSearch;
+                self.input_buffer.clear();
+                Ok(())
+            }
+            Action::ToggleCase => self.toggle_case_sensitive(),
+            Action::ToggleFuzzy => self.toggle_fuzzy_search(),
+            Action::ToggleSearchFilter => self.toggle_search_filter(),
+            Action::NextMatch => self.jump_to_match(times),
+            Action::PrevMatch => self.jump_to_match(-times),
+            Action::OwnerFilter => self.start_owner_filter(),
+            Action::ExtFilter => self.toggle_ext_filter(),
+            Action::FuzzyPick => {
+                self.pending_external = Some(External::FuzzyPicker);
                 Ok(())
             }
+            Action::Escape => self.handle_esc(),
 
-            _ => Ok(())
+            // exit
+            Action::Queue => self.open_queue_view(),
+            Action::Quit => self.start_quit(),
         }
     }
 
     ///
     /// # Guide
     ///
-    fn move_cursor(&mut self, delta: i32) -> Result<()> {
+    // `clamp` stops at the ends, otherwise it wraps around unless that is turned off
+    fn move_cursor(&mut self, delta: i32, clamp: bool) -> Result<()> {
         let len = self.filtered_files().len();
         if len == 0 {
             self.table_state.select(None);
@@ -190,13 +882,8 @@ impl App {
         }
 
         let new_index = match self.table_state.selected() {
-            Some(i) => {
-                if delta > 0 {
-                    if i >= len - 1 { 0 } else { i + 1 }
-                } else {
-                    if i == 0 { len - 1 } else { i - 1 }
-                }
-            },
+            Some(i) if clamp || !self.config.wrap_navigation => (i as i64 + delta as i64).clamp(0, len as i64 - 1) as usize,
+            Some(i) => (i as i64 + delta as i64).rem_euclid(len as i64) as usize,
             None => 0,
         };
 
@@ -204,8 +891,37 @@ impl App {
         Ok(())
     }
 
+    // whole or half a screen of rows, `fraction` of the table's last rendered height; stops at the ends
+    fn move_page(&mut self, fraction: f32) -> Result<()> {
+        let rows = (self.page_rows as f32 * fraction.abs()).round().max(1.0) as i32;
+        self.move_cursor(if fraction < 0.0 { -rows } else { rows }, true)
+    }
+
+    // "10G": the tenth visible row, the last one if there are fewer
+    fn cursor_to_row(&mut self, row: usize) -> Result<()> {
+        let len = self.filtered_files().len();
+        self.table_state.select(match len {
+            0 => None,
+            _ => Some(row.clamp(1, len) - 1),
+        });
+        Ok(())
+    }
+
+    // first or last visible row, filters and search included
+    fn jump_cursor(&mut self, to_end: bool) -> Result<()> {
+        let len = self.filtered_files().len();
+        self.table_state.select(match len {
+            0 => None,
+            _ if to_end => Some(len - 1),
+            _ => Some(0),
+        });
+        Ok(())
+    }
+
     fn go_parent_dir(&mut self) -> Result<()> {
+        let previous = self.fs.current_dir().clone();
         self.fs.parent_dir()?;
+        self.record_navigation(previous);
         self.clear_selection(); // clear selection
         self.reset_cursor();    // clear cursor
         Ok(())
@@ -213,171 +929,1742 @@ impl App {
 
     fn enter_current(&mut self) -> Result<()> {
         if let Some((original_index, is_dir)) = self.get_cursor_file_info() {
+            // links lead to where they point, not into a path that only exists through them
+            if self.fs.files()[original_index].link_target.is_some() {
+                return self.enter_symlink(original_index);
+            }
+            // a search result is shown where it lives
+            if !is_dir && self.fs.is_showing_found() {
+                let path = self.fs.files()[original_index].path.clone();
+                self.fs.leave_found();
+                return self.reveal(&path);
+            }
             if is_dir {
-                self.fs.select_current(original_index);
+                let previous = self.fs.current_dir().clone();
                 self.fs.sub_dir(original_index)?;
+                self.record_navigation(previous);
 
                 self.search_query.clear();
                 self.clear_selection();
                 self.reset_cursor();
+            } else {
+                // files open in the editor, same as `e`
+                let path = self.fs.files()[original_index].path.clone();
+                self.pending_external = Some(External::Editor(path));
             }
         }
 
         Ok(())
     }
 
-    ///
-    /// # Select Operation
-    ///
-    fn toggle_selection(&mut self) -> Result<()> {
-        if let Some((original_index, _)) = self.get_cursor_file_info() {
-            if self.fs.selected_index() == Some(original_index) {
-                self.fs.selected_index = None;
-            } else {
-                self.fs.selected_index = Some(original_index);
+    fn enter_symlink(&mut self, original_index: usize) -> Result<()> {
+        let file = &self.fs.files()[original_index];
+        let target = match file.path.canonicalize() {
+            Ok(target) => target,
+            Err(_) => {
+                let points_to = file.link_target.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+                self.fs.status_info = format!("Broken Link: {}", points_to);
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
             }
+        };
+
+        if target.is_dir() {
+            self.jump_to_dir(target)
+        } else {
+            self.pending_external = Some(External::Editor(target));
+            Ok(())
         }
-        Ok(())
-    }
-    fn clear_selection(&mut self){
-        self.fs.selected_index = None;
-    }
-    fn reset_cursor(&mut self) {
-        // if nothing in current dir(after search), current index should be None
-        let filtered = self.filtered_files();
-        self.table_state.select(if filtered.is_empty() {None} else {Some(0)});
     }
 
-    ///
-    /// # File Operation
-    ///
-    fn start_delete_confirm(&mut self) -> Result<()> {
-        if self.fs.selected_index.is_some(){
-            self.input_context = InputContext::ConfirmDelete;
+    // jump to where the link under the cursor really points, files get selected in their dir
+    fn follow_symlink(&mut self) -> Result<()> {
+        let link = match self.get_cursor_file_info() {
+            Some((original_index, _)) => self.fs.files()[original_index].path.clone(),
+            None => return Ok(()),
+        };
+        if !link.is_symlink() {
+            self.fs.status_info = "Not Symlink".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+
+        let target = match link.canonicalize() {
+            Ok(target) => target,
+            Err(_) => {
+                let points_to = std::fs::read_link(&link).map(|path| path.display().to_string()).unwrap_or_default();
+                self.fs.status_info = format!("Broken Link: {}", points_to);
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let (dir, file_name) = if target.is_dir() {
+            (target, None)
         } else {
-            self.exit_input_mode()
+            match (target.parent(), target.file_name()) {
+                (Some(parent), Some(name)) => (parent.to_path_buf(), Some(name.to_string_lossy().into_owned())),
+                _ => return Ok(()),
+            }
+        };
+
+        let previous = self.fs.current_dir().clone();
+        self.fs.goto_dir(dir, true)?;
+        self.record_navigation(previous);
+        self.after_jump();
+
+        if let Some(name) = file_name {
+            // whatever the cursor landed on instead is not what the link points at
+            if !self.focus_file(&name) {
+                self.fs.status_info = format!("{} Is Hidden or Filtered Out", name);
+                self.fs.status_flag = StatusFlag::Error;
+            } else if let Some((original_index, _)) = self.get_cursor_file_info() {
+                self.fs.mark(original_index);
+            }
         }
         Ok(())
     }
 
-    fn start_rename(&mut self) -> Result<()> {
-        if let Some((original_index, _)) = self.get_cursor_file_info() {
-            if let Some(file) = self.fs.files().clone().get(original_index) {
-                self.fs.selected_index = Some(original_index);
-                self.input_buffer = file.name.clone();
-                self.input_context = InputContext::Rename;
+    // the ones with an argument go through the same path as their own prompt
+    fn run_command(&mut self, line: &str) -> Result<()> {
+        let command = match command::parse(line) {
+            Ok(command) => command,
+            Err(error) => {
+                self.fs.status_info = error.to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
             }
+        };
+        match command {
+            Command::Action(action) => self.run_action(action, None),
+            Command::Sort(mode) => self.preserving_cursor(|app| {
+                app.fs.set_sort(mode);
+                Ok(())
+            }),
+            Command::Mkdir(path) => self.submit_as(InputContext::NewDir, path),
+            Command::Touch(names) => self.submit_as(InputContext::NewFile, names),
+            Command::Rename(name) if self.listed_marks().0.len() > 1 => self.submit_as(InputContext::BatchRename, name),
+            Command::Rename(name) => self.submit_as(InputContext::Rename, name),
+            Command::GoTo(path) => self.submit_as(InputContext::GoTo, path),
+            Command::Search(query) => self.submit_as(InputContext::Search, query),
+            Command::Shell(command) => self.submit_as(InputContext::Shell, command),
         }
-        Ok(())
     }
 
-    fn start_new_file(&mut self) -> Result<()> {
-        self.input_context = InputContext::NewFile;
-        self.input_buffer.clear();
-        Ok(())
+    fn submit_as(&mut self, context: InputContext, input: String) -> Result<()> {
+        self.input_context = context;
+        self.input_buffer = input;
+        self.submit_input()
     }
 
-    fn start_new_dir(&mut self) -> Result<()> {
-        self.input_context = InputContext::NewDir;
+    // output is summed up in the status bar; a leading "!" hands the terminal over instead
+    fn run_shell(&mut self, input: &str) -> Result<()> {
+        let path = self.get_cursor_file_info().map(|(original_index, _)| self.fs.files()[original_index].path.clone());
+        let (interactive, command) = match input.strip_prefix('!') {
+            Some(command) => (true, command.trim()),
+            None => (false, input),
+        };
+        let command = external::substitute_path(command, path.as_deref());
+
+        if interactive {
+            self.pending_external = Some(External::Shell(command));
+            return Ok(());
+        }
+        if let Some(captured) = &self.captured {
+            self.fs.status_info = format!("Still Running: {}", captured.command);
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        self.fs.status_info = format!("Running: {}", command);
+        self.fs.status_flag = StatusFlag::Others;
+        self.captured = Some(Captured::start(command));
+        Ok(())
+    }
+
+    // a background command is done: sum it up and show what it may have changed
+    fn update_captured(&mut self) {
+        let result = match self.captured.as_ref().and_then(Captured::result) {
+            Some(result) => result,
+            None => return,
+        };
+        self.captured = None;
+        let _ = self.refresh_in_place();
+        match result {
+            Ok((success, summary)) => {
+                self.fs.status_info = summary;
+                self.fs.status_flag = if success { StatusFlag::Others } else { StatusFlag::Error };
+            }
+            Err(error) => {
+                self.fs.status_info = format!("Error: {}", error);
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+    }
+
+    // like entering a dir: undoable CD, fresh listing, cursor on top; a missing dir only reports
+    fn jump_to_dir(&mut self, dir: PathBuf) -> Result<()> {
+        let previous = self.fs.current_dir().clone();
+        self.fs.goto_dir(dir, true)?;
+        if *self.fs.current_dir() != previous {
+            self.record_navigation(previous);
+            self.after_jump();
+        }
+        Ok(())
+    }
+
+    // absolute, "~/..." or relative to the current dir
+    fn go_to_path(&mut self, input: &str) -> Result<()> {
+        let path = self.fs.current_dir().join(expand_home(input));
+        match path.canonicalize() {
+            Ok(dir) if dir.is_dir() => self.jump_to_dir(dir),
+            Ok(dir) => {
+                self.fs.status_info = format!("Not Dir: {}", dir.display());
+                self.fs.status_flag = StatusFlag::Error;
+                Ok(())
+            }
+            Err(error) => {
+                self.fs.status_info = format!("{}: {}", path.display(), error);
+                self.fs.status_flag = StatusFlag::Error;
+                Ok(())
+            }
+        }
+    }
+
+    fn go_back(&mut self) -> Result<()> {
+        let target = match self.nav_back.pop() {
+            Some(dir) => dir,
+            None => {
+                self.fs.status_info = "No Previous Dir".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let current = self.fs.current_dir().clone();
+        self.fs.goto_dir(target, false)?;
+        if *self.fs.current_dir() != current {
+            self.nav_forward.push(current);
+            self.after_jump();
+        }
+        Ok(())
+    }
+
+    fn go_forward(&mut self) -> Result<()> {
+        let target = match self.nav_forward.pop() {
+            Some(dir) => dir,
+            None => {
+                self.fs.status_info = "No Next Dir".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let current = self.fs.current_dir().clone();
+        self.fs.goto_dir(target, false)?;
+        if *self.fs.current_dir() != current {
+            self.nav_back.push(current);
+            self.after_jump();
+        }
+        Ok(())
+    }
+
+    // a fresh navigation drops the forward history, like a browser
+    fn record_navigation(&mut self, previous: PathBuf) {
+        if previous == *self.fs.current_dir() {
+            return;
+        }
+        if self.nav_back.len() == MAX_NAV_HISTORY {
+            self.nav_back.remove(0);
+        }
+        self.nav_back.push(previous);
+        self.nav_forward.clear();
+        self.remember_recent_dir();
+    }
+
+    // the current dir to the front of the recent ones, back and forward are not visits
+    fn remember_recent_dir(&mut self) {
+        let dir = self.fs.current_dir().clone();
+        self.recent_dirs.retain(|old| *old != dir);
+        if self.recent_dirs.len() == MAX_RECENT_DIRS {
+            self.recent_dirs.pop_back();
+        }
+        self.recent_dirs.push_front(dir);
+    }
+
+    fn after_jump(&mut self) {
+        self.search_query.clear();
+        self.clear_selection();
+        self.reset_cursor();
+    }
+
+    ///
+    /// # Select Operation
+    ///
+    fn toggle_selection(&mut self) -> Result<()> {
+        if let Some((original_index, _)) = self.get_cursor_file_info() {
+            self.fs.toggle_mark(original_index);
+        }
+        Ok(())
+    }
+    fn clear_selection(&mut self){
+        self.fs.clear_marks();
+    }
+
+    // only what is listed, entries hidden by search or filters are left alone
+    fn mark_listed(&mut self, invert: bool) -> Result<()> {
+        let listed: Vec<usize> = self.filtered_files().iter().map(|(index, _)| *index).collect();
+        self.fs.mark_all(&listed, invert);
+        Ok(())
+    }
+
+    // several patterns in a row add up, "*.png" then "*.jpg"
+    fn mark_matching(&mut self, pattern: &str) {
+        let matcher = match Glob::new(pattern) {
+            Ok(glob) => glob.compile_matcher(),
+            Err(error) => {
+                self.fs.status_info = format!("Bad Pattern: {}", error.kind());
+                self.fs.status_flag = StatusFlag::Error;
+                return;
+            }
+        };
+        let matching: Vec<usize> = self.filtered_files()
+            .iter()
+            .filter(|(_, file)| matcher.is_match(&file.name))
+            .map(|(index, _)| *index)
+            .collect();
+        if matching.is_empty() {
+            self.fs.status_info = format!("No Match: {}", pattern);
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.fs.mark_all(&matching, false);
+        }
+    }
+
+    // marks are indices into the whole listing, the table only shows part of it
+    // marks on listed entries, a search or filter may hide others: (listed ones, how many are hidden);
+    // operations take only these and leave the hidden marks as they are
+    fn listed_marks(&self) -> (Vec<usize>, usize) {
+        let listed: HashSet<usize> = self.filtered_files().iter().map(|(index, _)| *index).collect();
+        let (shown, hidden): (Vec<usize>, Vec<usize>) = self.fs.marked_indices()
+            .into_iter()
+            .partition(|index| listed.contains(index));
+        (shown, hidden.len())
+    }
+
+    fn note_hidden_marks(&mut self, hidden: usize) {
+        if hidden > 0 {
+            self.fs.status_info = format!("{}, {}", self.fs.status_info, hidden_marks_note(hidden));
+        }
+    }
+
+    fn unmark_all(&mut self) -> Result<()> {
+        self.clear_selection();
+        self.fs.status_info = "Marks Cleared".to_string();
+        self.fs.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+    // cursor onto `name` when it is listed, e.g. a freshly created file
+    // false when it is not listed (gone, hidden or filtered out), the cursor is only kept in range then
+    fn focus_file(&mut self, name: &str) -> bool {
+        let position = self.filtered_files()
+            .iter()
+            .position(|(_, file)| file.name == name);
+        match position {
+            Some(position) => self.table_state.select(Some(position)),
+            None => self.clamp_cursor(),
+        }
+        position.is_some()
+    }
+
+    // the file under the cursor stays under it; if it is gone, the cursor stays on its row
+    fn preserving_cursor<T>(&mut self, op: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let name = self.get_cursor_file_info().map(|(original_index, _)| self.fs.files()[original_index].name.clone());
+        let result = op(self);
+        match name {
+            Some(name) => {
+                self.focus_file(&name);
+            }
+            None => self.clamp_cursor(),
+        }
+        result
+    }
+
+    // keep the cursor valid after the listing changed size
+    fn clamp_cursor(&mut self) {
+        let len = self.filtered_files().len();
+        match self.table_state.selected() {
+            _ if len == 0 => self.table_state.select(None),
+            Some(i) if i >= len => self.table_state.select(Some(len - 1)),
+            None => self.table_state.select(Some(0)),
+            _ => {}
+        }
+    }
+
+    fn reset_cursor(&mut self) {
+        // if nothing in current dir(after search), current index should be None
+        let filtered = self.filtered_files();
+        self.table_state.select(if filtered.is_empty() {None} else {Some(0)});
+    }
+
+    ///
+    /// # File Operation
+    ///
+    // every marked entry, or the one under the cursor when nothing is marked
+    fn copy_current(&mut self, is_copy: bool) -> Result<()> {
+        let (marked, hidden) = self.listed_marks();
+        match self.get_cursor_file_info() {
+            _ if !marked.is_empty() => self.fs.copy_entries(&marked, is_copy)?,
+            Some((original_index, _)) => self.fs.copy_entries(&[original_index], is_copy)?,
+            None => {
+                self.fs.status_info = "No File Selected".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+        // the clipboard keeps the path, a leftover selection would only redirect the paste
+        self.fs.unmark(&marked);
+        self.note_hidden_marks(hidden);
+        Ok(())
+    }
+
+    // from the upper of two marked entries to the lower one, as an import or link would spell it
+    fn copy_relative_path(&mut self) -> Result<()> {
+        let (from, to) = match self.fs.marked_indices().as_slice() {
+            [from, to] => (&self.fs.files()[*from], &self.fs.files()[*to]),
+            _ => {
+                self.fs.status_info = "Mark Exactly Two Entries".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        // a file is referenced from the dir it sits in
+        let base = if from.is_dir { from.path.clone() } else { from.path.parent().map(PathBuf::from).unwrap_or_default() };
+        let relative = relative_path(&base, &to.path).display().to_string();
+        self.copy_text(relative);
+        Ok(())
+    }
+
+    // the text, not the file, goes to the system clipboard; the name alone with `name_only`
+    fn copy_cursor_path(&mut self, name_only: bool) -> Result<()> {
+        match self.get_cursor_file_info() {
+            Some((original_index, _)) => {
+                let file = &self.fs.files()[original_index];
+                let text = if name_only { file.name.clone() } else { self.fs.current_dir().join(&file.name).display().to_string() };
+                self.copy_text(text);
+            }
+            None => {
+                self.fs.status_info = "No File Selected".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+        Ok(())
+    }
+
+    // without a clipboard tool (headless, serial console) the status bar shows it to copy by hand
+    fn copy_text(&mut self, text: String) {
+        match external::copy_to_clipboard(&text) {
+            Ok(()) => {
+                self.fs.status_info = format!("Copied: {}", text);
+                self.fs.status_flag = StatusFlag::Others;
+            }
+            Err(error) => {
+                self.fs.status_info = format!("{} (not copied: {})", text, error);
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+    }
+
+    fn edit_current(&mut self) -> Result<()> {
+        if let Some((original_index, false)) = self.get_cursor_file_info() {
+            let path = self.fs.files()[original_index].path.clone();
+            self.pending_external = Some(External::Editor(path));
+        }
+        Ok(())
+    }
+
+    fn edit_file(&mut self, path: PathBuf) -> Result<()> {
+        external::open_in_editor(&path)?;
+        self.remember_recent_file(path);
+        // the editor may have written or created files
+        self.fs.refresh()?;
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    fn remember_recent_file(&mut self, path: PathBuf) {
+        self.recent_files.retain(|old| *old != path);
+        if self.recent_files.len() == MAX_RECENT_FILES {
+            self.recent_files.pop_back();
+        }
+        self.recent_files.push_front(path);
+        // losing the list is not worth interrupting the user for
+        let _ = state::save_paths(RECENT_FILES, &self.recent_files);
+    }
+
+    fn open_with_picker(&mut self) -> Result<()> {
+        if !matches!(self.get_cursor_file_info(), Some((_, false))) {
+            self.fs.status_info = "Not File".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        let items: Vec<String> = self.config.open_with
+            .iter()
+            .map(|app| format!("{}  ({})", app.name, app.command))
+            .collect();
+
+        if items.is_empty() {
+            self.fs.status_info = "No Apps Configured (open_with)".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.picker = Some(Picker::new(PickerKind::OpenWith, "Open With", items));
+        }
+        Ok(())
+    }
+
+    // whatever the desktop associates with the file, the editor when there is no such thing
+    fn open_with_default(&mut self) -> Result<()> {
+        let path = match self.get_cursor_file_info() {
+            Some((original_index, false)) => self.fs.files()[original_index].path.clone(),
+            _ => {
+                self.fs.status_info = "Not File".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+
+        match external::spawn_detached(opener, &path) {
+            Ok(()) => {
+                self.fs.status_info = format!("Opened: {}", path.file_name().unwrap_or_default().to_string_lossy());
+                self.fs.status_flag = StatusFlag::Others;
+            }
+            Err(_) => self.pending_external = Some(External::Editor(path)),
+        }
+        Ok(())
+    }
+
+    fn open_with_app(&mut self, path: PathBuf, app: OpenWithApp) {
+        if !app.gui {
+            self.pending_external = Some(External::OpenWith(app.command, path));
+            return;
+        }
+
+        match external::spawn_detached(&app.command, &path) {
+            Ok(()) => {
+                self.fs.status_info = format!("Launched: {}", app.name);
+                self.fs.status_flag = StatusFlag::Others;
+            }
+            Err(error) => {
+                self.fs.status_info = format!("Launch Failed: {}", error);
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+    }
+
+    // bookmark the current dir, or drop it if it already is one
+    fn toggle_bookmark(&mut self) -> Result<()> {
+        let dir = self.fs.current_dir().clone();
+        if let Some(position) = self.bookmarks.iter().position(|bookmark| *bookmark == dir) {
+            self.bookmarks.remove(position);
+            self.fs.status_info = format!("Bookmark Removed: {}", dir.display());
+        } else {
+            self.bookmarks.push(dir.clone());
+            self.fs.status_info = format!("Bookmarked: {}", dir.display());
+        }
+        self.fs.status_flag = StatusFlag::Others;
+
+        if let Err(error) = state::save_paths(BOOKMARKS, &self.bookmarks) {
+            self.fs.status_info = format!("Bookmarks Not Saved: {}", error);
+            self.fs.status_flag = StatusFlag::Error;
+        }
+        Ok(())
+    }
+
+    // dirs that are gone stay listed, picking one just reports it
+    fn open_bookmarks_picker(&mut self) -> Result<()> {
+        let items: Vec<String> = self.bookmarks
+            .iter()
+            .map(|dir| format!("{}{}", dir.display(), if dir.is_dir() { "" } else { "  [Missing]" }))
+            .collect();
+
+        if items.is_empty() {
+            self.fs.status_info = "No Bookmarks (B adds one)".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.picker = Some(Picker::new(PickerKind::Bookmarks, "Bookmarks", items));
+        }
+        Ok(())
+    }
+
+    // the current dir is where the list starts anyway, it is left out
+    fn open_recent_dirs_picker(&mut self) -> Result<()> {
+        self.recent_dirs.retain(|dir| dir.is_dir());
+        let items: Vec<String> = self.recent_dirs
+            .iter()
+            .skip_while(|dir| *dir == self.fs.current_dir())
+            .map(|dir| dir.display().to_string())
+            .collect();
+
+        if items.is_empty() {
+            self.fs.status_info = "No Recent Dirs".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.picker = Some(Picker::new(PickerKind::RecentDirs, "Recent Dirs", items));
+        }
+        Ok(())
+    }
+
+    fn open_recent_files_picker(&mut self) -> Result<()> {
+        self.recent_files.retain(|path| path.exists());
+        let items: Vec<String> = self.recent_files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+
+        if items.is_empty() {
+            self.fs.status_info = "No Recent Files".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.picker = Some(Picker::new(PickerKind::RecentFiles, "Recent Files", items));
+        }
+        Ok(())
+    }
+
+    fn open_clipboard_picker(&mut self) -> Result<()> {
+        let items: Vec<String> = self.fs.clipboard_history()
+            .iter()
+            .map(|(path, is_copy)| format!("[{}] {}", if *is_copy { "Copy" } else { "Cut" }, path.display()))
+            .collect();
+
+        if items.is_empty() {
+            self.fs.status_info = "Clipboard is empty".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+        } else {
+            self.picker = Some(Picker::new(PickerKind::Clipboard, "Paste from Clipboard", items));
+        }
+        Ok(())
+    }
+
+    // Enter on a waiting job cancels it
+    fn open_queue_view(&mut self) -> Result<()> {
+        let (running, pending) = self.fs.queue().snapshot();
+        if running.is_none() && pending.is_empty() {
+            self.fs.status_info = "No Operations Running".to_string();
+            self.fs.status_flag = StatusFlag::Others;
+            return Ok(());
+        }
+
+        let title = match running {
+            Some(label) => format!("Running: {} (Enter cancels a queued one)", label),
+            None => "Queue (Enter cancels)".to_string(),
+        };
+        let items = pending.iter().map(|(_, label)| label.clone()).collect();
+        self.queued_ids = pending.into_iter().map(|(id, _)| id).collect();
+        self.picker = Some(Picker::new(PickerKind::Queue, &title, items));
+        Ok(())
+    }
+
+    fn flatten_current(&mut self) -> Result<()> {
+        let dir = match self.get_cursor_file_info() {
+            Some((original_index, _)) => self.fs.files()[original_index].path.clone(),
+            None => return Ok(()),
+        };
+        let conflicts = self.fs.flatten_conflicts(&dir);
+        if conflicts == 0 {
+            let policy = self.fs.default_conflict();
+            return self.preserving_cursor(|app| app.fs.flatten_into_current(&dir, policy));
+        }
+        self.confirm_message = format!(
+            "{} already exist{} here: [o]verwrite (keeps name~), [r]ename, [s]kip, Esc cancels",
+            conflicts, if conflicts == 1 { "s" } else { "" }
+        );
+        self.flatten_dir = Some(dir);
+        self.input_context = InputContext::FlattenConflict;
         self.input_buffer.clear();
         Ok(())
     }
 
-    ///
-    /// # Search
-    ///
-    fn toggle_hidden_files(&mut self) -> Result<()> {
-        self.show_hidden = !self.show_hidden; // toggle status
-        self.search_query.clear();      // clear search buffer
+    fn answer_flatten_conflict(&mut self, key: KeyCode) -> Result<()> {
+        let policy = match key {
+            KeyCode::Char('o') | KeyCode::Char('O') => Conflict::Overwrite,
+            KeyCode::Char('r') | KeyCode::Char('R') => Conflict::Rename,
+            KeyCode::Char('s') | KeyCode::Char('S') => Conflict::Skip,
+            KeyCode::Esc => {
+                self.flatten_dir = None;
+                self.exit_input_mode();
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        self.close_input();
+        match self.flatten_dir.take() {
+            Some(dir) => self.preserving_cursor(|app| app.fs.flatten_into_current(&dir, policy)),
+            None => Ok(()),
+        }
+    }
+
+    fn duplicate_current(&mut self) -> Result<()> {
+        match self.get_cursor_file_info() {
+            Some((original_index, _)) => self.fs.duplicate(original_index)?,
+            None => {
+                self.fs.status_info = "No File Selected".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+        Ok(())
+    }
+
+    fn extract_current(&mut self) -> Result<()> {
+        match self.get_cursor_file_info() {
+            Some((original_index, _)) => self.fs.extract(original_index)?,
+            None => {
+                self.fs.status_info = "No File Selected".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+            }
+        }
+        Ok(())
+    }
+
+    // a name is suggested: the single entry's, otherwise the current dir's
+    fn start_create_archive(&mut self) -> Result<()> {
+        let (marked, _) = self.listed_marks();
+        let suggested = match (marked.as_slice(), self.get_cursor_file_info()) {
+            ([_, _, ..], _) => self.fs.current_dir().file_name().map(|name| name.to_string_lossy().into_owned()),
+            (&[index], _) | (&[], Some((index, _))) => Some(self.fs.files()[index].name.clone()),
+            ([], None) => None,
+        };
+        let suggested = match suggested {
+            Some(name) => name,
+            None => {
+                self.fs.status_info = "No File Selected".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        self.input_buffer = format!("{}.tar.gz", suggested);
+        self.input_context = InputContext::ArchiveName;
+        Ok(())
+    }
+
+    fn create_archive(&mut self, name: &str) -> Result<()> {
+        let (marked, hidden) = self.listed_marks();
+        let indices = match self.get_cursor_file_info() {
+            _ if !marked.is_empty() => marked,
+            Some((original_index, _)) => vec![original_index],
+            None => Vec::new(),
+        };
+        self.fs.create_archive(&indices, name)?;
+        self.fs.unmark(&indices);
+        self.note_hidden_marks(hidden);
+        Ok(())
+    }
+
+    fn toggle_executable(&mut self) -> Result<()> {
+        if let Some((original_index, _)) = self.get_cursor_file_info() {
+            self.preserving_cursor(|app| app.fs.toggle_executable(original_index))?;
+        }
+        Ok(())
+    }
+
+    fn touch_current(&mut self) -> Result<()> {
+        if let Some((original_index, _)) = self.get_cursor_file_info() {
+            self.preserving_cursor(|app| app.fs.touch(original_index))?;
+        }
+        Ok(())
+    }
+
+    fn start_delete_confirm(&mut self) -> Result<()> {
+        // a search or filter changed after marking may hide some, only what is seen goes
+        let (marked, hidden) = self.listed_marks();
+        if !marked.is_empty() {
+            let paths = marked.iter().map(|index| self.fs.files()[*index].path.clone()).collect();
+            self.confirm_size = Some(DiskUsage::start(paths));
+            let count = match marked.len() {
+                1 => "1 item".to_string(),
+                count => format!("{} items", count),
+            };
+            self.confirm_message = if self.fs.uses_trash() {
+                format!("Move {} ({{size}}) to trash? (y/N): ", count)
+            } else {
+                format!("Delete {} ({{size}})? Cannot be undone (y/N): ", count)
+            };
+            if hidden > 0 {
+                self.confirm_message = format!("{}; {}", hidden_marks_note(hidden), self.confirm_message);
+            }
+            self.input_context = InputContext::ConfirmDelete;
+        } else {
+            self.exit_input_mode()
+        }
+        Ok(())
+    }
+
+    // names already taken in the target dir are asked about first;
+    // `target` is the other pane's dir, None pastes here
+    fn start_paste(&mut self, target: Option<PathBuf>) -> Result<()> {
+        let conflicts = self.fs.paste_conflicts(target.as_deref());
+        if conflicts == 0 {
+            return self.paste_into(self.fs.default_conflict(), target);
+        }
+        self.confirm_message = format!(
+            "{} already exist{} {}: [o]verwrite (keeps name~), [r]ename, [s]kip, Esc cancels",
+            conflicts, if conflicts == 1 { "s" } else { "" }, if target.is_some() { "there" } else { "here" }
+        );
+        self.paste_target = target;
+        self.input_context = InputContext::PasteConflict;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn answer_paste_conflict(&mut self, key: KeyCode) -> Result<()> {
+        let policy = match key {
+            KeyCode::Char('o') | KeyCode::Char('O') => Conflict::Overwrite,
+            KeyCode::Char('r') | KeyCode::Char('R') => Conflict::Rename,
+            KeyCode::Char('s') | KeyCode::Char('S') => Conflict::Skip,
+            KeyCode::Esc => {
+                self.paste_target = None;
+                self.paste_link = false;
+                self.restore_clipboard();
+                self.exit_input_mode();
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        self.close_input();
+        let target = self.paste_target.take();
+        self.paste_into(policy, target)
+    }
+
+    // same conflict questions as a paste, symlinks to the clipboard entries instead of copies
+    fn start_link_paste(&mut self) -> Result<()> {
+        self.paste_link = true;
+        self.start_paste(None)
+    }
+
+    fn paste_into(&mut self, policy: Conflict, target: Option<PathBuf>) -> Result<()> {
+        let link = std::mem::take(&mut self.paste_link);
+        let pasted = self.preserving_cursor(|app| app.fs.paste_with(policy, target.as_deref(), link));
+        self.restore_clipboard();
+        pasted?;
+        // a move has already landed over there, a copy shows up when its job is done
+        if target.is_some() {
+            self.with_other_pane(|app| app.refresh_in_place()).transpose()?;
+        }
+        Ok(())
+    }
+
+    // like copy (or cut) followed by paste in the other pane, without switching to it
+    fn transfer_to_other_pane(&mut self, is_copy: bool) -> Result<()> {
+        let target = match &self.other_pane {
+            Some(pane) => pane.fs.current_dir().clone(),
+            None => {
+                self.fs.status_info = "No Other Pane, | Opens One".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        if target == *self.fs.current_dir() {
+            self.fs.status_info = "Both Panes Show This Dir".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        // nothing to take, the clipboard must not paste what it held before
+        if self.listed_marks().0.is_empty() && self.get_cursor_file_info().is_none() {
+            return self.copy_current(is_copy);
+        }
+        // the transfer goes through the clipboard, what the user copied comes back once it is pasted
+        self.saved_clipboard = Some(self.fs.take_clipboard());
+        if let Err(error) = self.copy_current(is_copy) {
+            self.restore_clipboard();
+            return Err(error);
+        }
+        self.start_paste(Some(target))
+    }
+
+    fn restore_clipboard(&mut self) {
+        if let Some(clipboard) = self.saved_clipboard.take() {
+            self.fs.set_clipboard(clipboard);
+        }
+    }
+
+    // leaving would kill a copy halfway through or forget a cut that was never pasted
+    fn start_quit(&mut self) -> Result<()> {
+        let other_busy = self.other_pane.as_ref().is_some_and(|pane| pane.fs.queue().is_busy());
+        let reason = if self.fs.queue().is_busy() || other_busy {
+            "Operations are still running"
+        } else if self.fs.has_cut() {
+            "Cut files are not pasted yet"
+        } else {
+            ""
+        };
+        if reason.is_empty() || !self.config.confirm_quit {
+            self.should_quit = true;
+            return Ok(());
+        }
+        self.confirm_message = format!("{}, quit anyway? (y/N): ", reason);
+        self.input_context = InputContext::ConfirmQuit;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn start_empty_dir_confirm(&mut self) -> Result<()> {
+        let files = self.fs.files();
+        if files.is_empty() {
+            self.fs.status_info = "Already Empty".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+
+        let count = files.len();
+        self.confirm_size = Some(DiskUsage::start(files.iter().map(|file| file.path.clone()).collect()));
+        let dir = self.fs.current_dir().display();
+        self.confirm_message = if self.fs.uses_trash() {
+            format!("Move ALL {} entries ({{size}}) in {} to the trash? Type yes: ", count, dir)
+        } else {
+            format!("PERMANENTLY delete ALL {} entries ({{size}}) in {}, no undo? Type yes: ", count, dir)
+        };
+        self.input_context = InputContext::ConfirmEmptyDir;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn start_rename(&mut self) -> Result<()> {
+        if self.listed_marks().0.len() > 1 {
+            self.input_buffer = "{name}{ext}".to_string();
+            self.input_context = InputContext::BatchRename;
+            return Ok(());
+        }
+        if let Some((original_index, _)) = self.get_cursor_file_info() {
+            if let Some(file) = self.fs.files().get(original_index) {
+                // a search result is listed by its relative path, only the last part is renamed
+                self.input_buffer = file.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| file.name.clone());
+                self.input_context = InputContext::Rename;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_new_file(&mut self) -> Result<()> {
+        self.input_context = InputContext::NewFile;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn start_new_dir(&mut self) -> Result<()> {
+        if self.config.enter_new_dirs {
+            return self.start_new_dir_enter();
+        }
+        self.input_context = InputContext::NewDir;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn start_new_dir_enter(&mut self) -> Result<()> {
+        self.input_context = InputContext::NewDirEnter;
+        self.input_buffer.clear();
+        Ok(())
+    }
+
+    fn new_dir_and_enter(&mut self, name: &str) -> Result<()> {
+        let path = self.fs.target_dir().join(name);
+        self.fs.new_file(name, true)?;
+        // creation failures only show up in the status, stay put then
+        if self.fs.status_flag == StatusFlag::Error {
+            return Ok(());
+        }
+
+        let previous = self.fs.current_dir().clone();
+        self.fs.goto_dir(path, true)?;
+        self.record_navigation(previous);
+        self.after_jump();
+        Ok(())
+    }
+
+    fn cycle_sort(&mut self) -> Result<()> {
+        self.fs.cycle_sort();
+        self.reset_cursor();
+        Ok(())
+    }
+
+    fn toggle_sort_order(&mut self) -> Result<()> {
+        self.preserving_cursor(|app| {
+            app.fs.toggle_sort_order();
+            Ok(())
+        })
+    }
+
+    fn toggle_owner_columns(&mut self) -> Result<()> {
+        self.show_owner = !self.show_owner;
+        Ok(())
+    }
+
+    fn toggle_permissions_column(&mut self) -> Result<()> {
+        self.show_permissions = !self.show_permissions;
+        Ok(())
+    }
+
+    fn toggle_modified_column(&mut self) -> Result<()> {
+        self.show_modified = !self.show_modified;
+        Ok(())
+    }
+
+    fn toggle_git_status(&mut self) -> Result<()> {
+        self.preserving_cursor(|app| app.fs.toggle_git_status())
+    }
+
+    fn toggle_preview(&mut self) -> Result<()> {
+        self.show_preview = !self.show_preview;
+        self.preview = None; // reread, the file may have changed meanwhile
+        Ok(())
+    }
+
+    fn toggle_hex_preview(&mut self) -> Result<()> {
+        self.hex_preview = !self.hex_preview;
+        self.preview_scroll = 0; // text lines and hex rows do not line up
+        Ok(())
+    }
+
+    // the renderer clamps it to the end of the content
+    fn scroll_preview(&mut self, delta: i32) -> Result<()> {
+        if self.show_preview {
+            self.preview_scroll = (self.preview_scroll as i32 + delta).clamp(0, u16::MAX as i32) as u16;
+        }
+        Ok(())
+    }
+
+    fn toggle_exact_sizes(&mut self) -> Result<()> {
+        self.exact_sizes = !self.exact_sizes;
+        Ok(())
+    }
+
+    ///
+    /// # Search
+    ///
+    fn toggle_hidden_files(&mut self) -> Result<()> {
+        self.show_hidden = !self.show_hidden; // toggle status
+        self.search_query.clear();      // clear search buffer
+        self.clear_selection();         // selected entry may be hidden now
+        self.reset_cursor();
+        Ok(())
+    }
+
+    // all, dirs only, files only
+    fn cycle_type_filter(&mut self) -> Result<()> {
+        self.type_filter = match self.type_filter {
+            TypeFilter::All => TypeFilter::DirsOnly,
+            TypeFilter::DirsOnly => TypeFilter::FilesOnly,
+            TypeFilter::FilesOnly => TypeFilter::All,
+        };
+        self.clear_selection();
+        self.reset_cursor(); // no cursor at all when nothing is left
+        Ok(())
+    }
+
+    fn toggle_ignored_files(&mut self) -> Result<()> {
+        self.clear_selection(); // same as hidden files, a mark must not end up out of sight
+        self.fs.toggle_hide_ignored()?;
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    fn start_search(&mut self) -> Result<()> {
+        self.search_before = (std::mem::take(&mut self.search_query), self.table_state.selected());
+        self.input_context = InputContext::Search;
+        self.input_buffer.clear(); // set input flag
+        self.cursor_to_search(); // clean search buffer
+        Ok(())
+    }
+
+    fn toggle_case_sensitive(&mut self) -> Result<()> {
+        self.case_sensitive = !self.case_sensitive;
+        self.clamp_cursor(); // the match set may have changed
+        self.fs.status_info = format!("Search: {}", self.search_mode_label());
+        self.fs.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    fn toggle_fuzzy_search(&mut self) -> Result<()> {
+        self.fuzzy_search = !self.fuzzy_search;
+        self.clamp_cursor(); // the match set may have changed
+        self.fs.status_info = format!("Search: {}", self.search_mode_label());
+        self.fs.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    fn toggle_search_filter(&mut self) -> Result<()> {
+        self.search_filters = !self.search_filters;
+        self.cursor_to_search();
+        self.fs.status_info = if self.search_filters { "Search: Filter" } else { "Search: Highlight" }.to_string();
+        self.fs.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    // a filtering search starts over at the top, a highlighting one at the first match
+    fn cursor_to_search(&mut self) {
+        if self.search_filters {
+            self.reset_cursor();
+        } else if let Some(row) = self.search_matches().first() {
+            self.table_state.select(Some(*row));
+        } else {
+            self.clamp_cursor();
+        }
+    }
+
+    // `steps` matches down the listing (up when negative), wrapping around the ends
+    fn jump_to_match(&mut self, steps: i32) -> Result<()> {
+        if self.search_query.is_empty() {
+            self.fs.status_info = "No Search".to_string();
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        let matches = self.search_matches();
+        if matches.is_empty() {
+            self.fs.status_info = format!("No Match: '{}'", self.search_query);
+            self.fs.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+
+        let len = matches.len() as i64;
+        let cursor = self.table_state.selected().unwrap_or(0);
+        // off a match, the first step lands on the neighbour in that direction
+        let position = match matches.binary_search(&cursor) {
+            Ok(position) => position as i64 + steps as i64,
+            Err(after) if steps > 0 => after as i64 + steps as i64 - 1,
+            Err(after) => after as i64 + steps as i64,
+        };
+        // the status bar tells which match this is
+        self.table_state.select(Some(matches[position.rem_euclid(len) as usize]));
+        Ok(())
+    }
+
+    fn handle_esc(&mut self) -> Result<()> {
+        // search results are left for the listing they replaced, a walk still going is stopped
+        if self.fs.is_showing_found() {
+            self.finder = None;
+            self.fs.leave_found();
+            self.preserving_cursor(|app| app.fs.refresh())?;
+            self.fs.status_info = "Search Closed".to_string();
+            self.fs.status_flag = StatusFlag::Others;
+            return Ok(());
+        }
+        // a quick filter is the most recent thing to back out of
+        if self.ext_filter.is_some() {
+            return self.toggle_ext_filter();
+        }
+        let (searching, marked) = (!self.search_query.is_empty(), self.fs.marked_count() > 0);
+        let clears = match self.config.esc_action {
+            EscAction::ClearSearch => searching,
+            EscAction::ClearMarks => marked,
+            EscAction::ClearBoth => searching || marked,
+            EscAction::None => false,
+        };
+        // a running copy is only stopped once there is nothing lighter left to back out of,
+        // an Esc too many must not throw away minutes of copying
+        if !clears && self.fs.queue().stop_running() {
+            self.fs.status_info = "Stopping Copy".to_string();
+            self.fs.status_flag = StatusFlag::Others;
+            return Ok(());
+        }
+        match self.config.esc_action {
+            EscAction::ClearSearch => self.clear_search()?,
+            EscAction::ClearMarks => self.clear_selection(),
+            EscAction::ClearBoth => {
+                self.clear_search()?;
+                self.clear_selection();
+            }
+            EscAction::None => {}
+        }
+        Ok(())
+    }
+
+    fn start_owner_filter(&mut self) -> Result<()> {
+        self.input_context = InputContext::OwnerFilter;
+        // Enter right away filters to our own files
+        self.input_buffer = self.owner_names.user(owner::current_uid());
+        Ok(())
+    }
+
+    // user name or uid, empty clears the filter
+    fn set_owner_filter(&mut self, user: &str) {
+        if user.is_empty() {
+            self.owner_filter = None;
+        } else if let Some(uid) = self.owner_names.uid_of(user) {
+            self.owner_filter = Some(uid);
+        } else {
+            self.fs.status_info = format!("Unknown User: {}", user);
+            self.fs.status_flag = StatusFlag::Error;
+            return;
+        }
+        self.clear_selection();
+        self.reset_cursor();
+    }
+
+    // asks for the extension, the cursor file's is filled in ("more like this"); again to clear
+    fn toggle_ext_filter(&mut self) -> Result<()> {
+        if self.ext_filter.take().is_some() {
+            self.clamp_cursor();
+            return Ok(());
+        }
+        self.input_buffer = match self.get_cursor_file_info() {
+            Some((original_index, false)) => file_system_info::extension_of(&self.fs.files()[original_index]).unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.input_context = InputContext::ExtFilter;
+        Ok(())
+    }
+
+    // "rs", ".rs" and "*.rs" all mean the same, case does not matter; empty clears
+    fn set_ext_filter(&mut self, input: &str) {
+        let ext = input.trim_start_matches('*').trim_start_matches('.').to_lowercase();
+        self.ext_filter = (!ext.is_empty()).then_some(ext);
+        self.clear_selection();
+        self.reset_cursor();
+    }
+
+    fn pick_with_fuzzy_finder(&mut self) -> Result<()> {
+        let names: Vec<String> = self.filtered_files()
+            .iter()
+            .map(|(_, file)| file.name.clone())
+            .collect();
+
+        let chosen = match external::fuzzy_pick(&self.config.picker_command, &names) {
+            Ok(Some(chosen)) => chosen,
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                self.fs.status_info = format!("Picker: {}", error);
+                self.fs.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        // mark every pick so the next operation acts on them, cursor on the first
+        let picked: Vec<usize> = self.filtered_files()
+            .iter()
+            .filter(|(_, file)| chosen.contains(&file.name))
+            .map(|(original_index, _)| *original_index)
+            .collect();
+        for original_index in picked {
+            self.fs.mark(original_index);
+        }
+        if let Some(name) = chosen.first() {
+            self.focus_file(name);
+        }
+        Ok(())
+    }
+
+    fn start_finder(&mut self, query: String) {
+        let mode = MatchMode {
+            fuzzy: self.fuzzy_search,
+            case_sensitive: self.case_sensitive,
+            show_hidden: self.show_hidden,
+        };
+        let root = self.fs.current_dir().clone();
+        self.finder = Some(Finder::start(root, query, self.config.search_depth, mode));
+        self.found_seen = 0;
+        self.fs.show_found();
+        self.search_query.clear();
         self.reset_cursor();
+        self.fs.status_info = "Searching...".to_string();
+        self.fs.status_flag = StatusFlag::Others;
+        self.update_finder();
+    }
+
+    fn start_checksum(&mut self) -> Result<()> {
+        let file = match self.get_cursor_file_info() {
+            Some((original_index, false)) => &self.fs.files()[original_index],
+            Some((_, true)) => {
+                self.fs.status_info = "Checksum: Not Applicable to Dirs".to_string();
+                self.fs.status_flag = StatusFlag::Others;
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        self.checksum = Some(Checksum::start(file.path.clone(), file.size));
+        self.picker = Some(Picker::new(PickerKind::Checksum, "Checksum", Vec::new()));
+        self.update_checksum();
         Ok(())
     }
 
-    fn start_search(&mut self) -> Result<()> {
-        self.input_context = InputContext::Search;
-        self.input_buffer.clear(); // set input flag
-        self.reset_cursor(); // clean search buffer
+    // progress in the title until the sums are in, then Enter copies the highlighted one
+    fn update_checksum(&mut self) {
+        let (checksum, picker) = match (&self.checksum, &mut self.picker) {
+            (Some(checksum), Some(picker)) if picker.kind == PickerKind::Checksum => (checksum, picker),
+            _ => return,
+        };
+        let name = checksum.path.file_name().unwrap_or_default().to_string_lossy();
+        match checksum.sums() {
+            Some(Ok((sha256, md5))) => picker.update(
+                &format!("{} (Enter copies)", name),
+                vec![format!("SHA-256  {}", sha256), format!("MD5      {}", md5)],
+            ),
+            Some(Err(error)) => picker.update(&format!("{}: {}", name, error), Vec::new()),
+            None => {
+                let percent = if checksum.size == 0 { 0.0 } else { checksum.read() as f64 / checksum.size as f64 * 100.0 };
+                picker.update(&format!("{}: hashing {:.0}%...", name, percent.min(100.0)), Vec::new());
+            }
+        }
+    }
+
+    // the walk keeps going in the background, pull what it found since the last tick into the listing
+    fn update_finder(&mut self) {
+        let finder = match &self.finder {
+            Some(finder) => finder,
+            None => return,
+        };
+        // the results were left (Esc, another dir), nothing is waiting for the rest
+        if !self.fs.is_showing_found() {
+            self.finder = None;
+            return;
+        }
+        // asked before taking the results, so none that came in between are missed
+        let done = finder.is_done();
+        let found = finder.found_since(self.found_seen);
+        if found.is_empty() && !done {
+            return;
+        }
+
+        self.found_seen += found.len();
+        self.fs.status_info = format!(
+            "'{}' below {}: {} found{}",
+            finder.query, finder.root.display(), self.found_seen, if done { "" } else { ", searching..." }
+        );
+        self.fs.status_flag = StatusFlag::Others;
+        let _ = self.preserving_cursor(|app| {
+            app.fs.add_found(&found);
+            Ok(())
+        });
+        if done {
+            self.finder = None;
+        }
+    }
+
+    // into the dir holding `path`, cursor on it
+    fn reveal(&mut self, path: &Path) -> Result<()> {
+        let (dir, name) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => (dir.to_path_buf(), name.to_string_lossy().into_owned()),
+            _ => return Ok(()),
+        };
+        let previous = self.fs.current_dir().clone();
+        self.fs.goto_dir(dir, true)?;
+        self.record_navigation(previous);
+        self.after_jump();
+        self.focus_file(&name);
+        Ok(())
+    }
+
+    // someone else changed the dir: same file under the cursor, same status message
+    fn refresh_in_place(&mut self) -> Result<()> {
+        let status = (self.fs.status_info.clone(), self.fs.status_flag);
+        let dir = self.fs.current_dir().clone();
+        self.preserving_cursor(|app| app.fs.refresh())?;
+        // unless the dir itself went away, that is news
+        if *self.fs.current_dir() == dir {
+            (self.fs.status_info, self.fs.status_flag) = status;
+        }
         Ok(())
     }
 
     fn clear_search(&mut self) -> Result<()> {
         if !self.search_query.is_empty() {
             self.search_query.clear();
-            self.reset_cursor();
+            self.cursor_to_search();
+        }
+        Ok(())
+    }
+
+    ///
+    /// # Panes
+    ///
+    fn toggle_dual_pane(&mut self) -> Result<()> {
+        match &self.other_pane {
+            None => self.open_other_pane(),
+            // dropping it would drop its queue with whatever is still running
+            Some(pane) if pane.fs.queue().is_busy() => {
+                self.fs.status_info = "Operations Running in the Other Pane".to_string();
+                self.fs.status_flag = StatusFlag::Error;
+                Ok(())
+            }
+            Some(_) => {
+                self.other_pane = None;
+                self.active_left = true;
+                Ok(())
+            }
+        }
+    }
+
+    // starts in the same dir, the way most two-pane managers do
+    fn open_other_pane(&mut self) -> Result<()> {
+        self.other_pane = Some(Pane {
+            fs: FileSys::init(self.fs.current_dir().clone(), &self.config)?,
+            table_state: TableState::default(),
+            search_query: String::new(),
+            nav_back: Vec::new(),
+            nav_forward: Vec::new(),
+            finder: None,
+            found_seen: 0,
+        });
+        self.with_other_pane(|app| app.reset_cursor());
+        Ok(())
+    }
+
+    fn switch_pane(&mut self) -> Result<()> {
+        if self.other_pane.is_none() {
+            self.open_other_pane()?;
         }
+        // one clipboard for both, "c" here and "v" there copies across
+        let clipboard = self.fs.take_clipboard();
+        self.swap_pane_state();
+        self.fs.set_clipboard(clipboard);
+        self.active_left = !self.active_left;
+        self.preview = None;
+        self.preview_scroll = 0;
         Ok(())
     }
 
+    // runs `op` as if the other pane had the focus; None in single-pane view
+    fn with_other_pane<T>(&mut self, op: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        if !self.swap_pane_state() {
+            return None;
+        }
+        let result = op(self);
+        self.swap_pane_state();
+        Some(result)
+    }
+
+    fn swap_pane_state(&mut self) -> bool {
+        let pane = match self.other_pane.as_mut() {
+            Some(pane) => pane,
+            None => return false,
+        };
+        std::mem::swap(&mut self.fs, &mut pane.fs);
+        std::mem::swap(&mut self.table_state, &mut pane.table_state);
+        std::mem::swap(&mut self.search_query, &mut pane.search_query);
+        std::mem::swap(&mut self.nav_back, &mut pane.nav_back);
+        std::mem::swap(&mut self.nav_forward, &mut pane.nav_forward);
+        std::mem::swap(&mut self.finder, &mut pane.finder);
+        std::mem::swap(&mut self.found_seen, &mut pane.found_seen);
+        true
+    }
+
     ///
     /// # UI
     ///
     fn ui(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            let widget = Paragraph::new(format!("Terminal too small\n{}x{}, need {}x{}", area.width, area.height, MIN_WIDTH, MIN_HEIGHT))
+                .style(Style::default().fg(self.theme.error));
+            frame.render_widget(widget, area);
+            return;
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
             .constraints([Constraint::Min(1), Constraint::Length(3)])
-            .split(frame.area());
+            .split(area);
 
-        self.render_table(frame, chunks[0]);
+        if self.other_pane.is_some() || self.show_preview {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[0]);
+            // the second pane takes the place of the preview
+            let (active, other) = if self.active_left { (panes[0], panes[1]) } else { (panes[1], panes[0]) };
+            self.other_area = self.with_other_pane(|app| app.render_table(frame, other, false)).map(|_| other);
+            if self.other_area.is_none() {
+                self.render_preview(frame, other);
+            }
+            self.render_table(frame, active, true);
+        } else {
+            self.other_area = None;
+            self.render_table(frame, chunks[0], true);
+        }
         self.render_status_bar(frame, chunks[1]);
+
+        if let Some(picker) = self.picker.as_mut() {
+            picker.render(frame);
+        }
     }
 
-    fn render_table(&mut self, frame: &mut Frame, area: Rect) {
+    // the inactive pane is dimmed and shows no cursor
+    fn render_table(&mut self, frame: &mut Frame, area: Rect, active: bool) {
+        // borders and header take three lines
+        if active {
+            self.page_rows = area.height.saturating_sub(3);
+            self.table_area = area;
+        }
         // only show filtered files
         let table = self.filtered_files();
 
-        let rows: Vec<Row> = table.iter().map(|(index, file)| {
-            let style = if Some(*index) == self.fs.selected_index(){
-                Style::default().add_modifier(Modifier:: BOLD).fg(Color::Cyan) // selected
+        let size_text = if self.exact_sizes { format_bytes_exact } else { format_file_size };
+        let size_width = if self.exact_sizes { 18 } else { 12 };
+        let owner_width = if self.show_owner { 2 * (OWNER_WIDTH + 1) } else { 0 };
+        let git_width = if self.fs.git_status() { 2 } else { 0 };
+        let permissions_width = if self.show_permissions { PERMISSIONS_WIDTH + 1 } else { 0 };
+        let modified_width = if self.show_modified { MODIFIED_WIDTH + 1 } else { 0 };
+        // borders, size, type ("SYMLINK"), permissions, modified, owner and git columns and the spacing between them
+        let name_width = area.width.saturating_sub(2 + size_width + 7 + 2 + permissions_width + modified_width + owner_width + git_width) as usize;
+
+        let highlight = !self.search_filters && !self.search_query.is_empty();
+        let match_style = Style::default().fg(self.theme.search_match).add_modifier(Modifier::BOLD);
+        let (rows, heights): (Vec<Row>, Vec<u16>) = table.iter().map(|(index, file)| {
+            let mut style = if self.fs.is_marked(*index) {
+                Style::default().add_modifier(Modifier:: BOLD).fg(self.theme.marked) // selected
             } else {
-                Style::default() // not selected
+                file_style(file, &self.theme) // not selected
+            };
+            if highlight && self.search_score(&file.name).is_some() {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+
+            let mut name = match (&file.link_target, file.broken_link) {
+                (Some(target), false) => format!("{} -> {}", file.name, target.display()),
+                (Some(target), true) => format!("{} -> {} [broken]", file.name, target.display()),
+                (None, _) => file.name.clone(),
             };
+            let mut matched = self.match_positions(&file.name);
+            if self.config.icons {
+                let icon = icons::icon(file);
+                name = format!("{} {}", icon, name);
+                // the name moved right by the glyph and its space
+                matched.iter_mut().for_each(|index| *index += icon.chars().count() + 1);
+            }
+            let width = if self.config.wrap_names { name_width } else { usize::MAX };
+            let name = wrap_name(&name, width, &matched, match_style);
+            let height = name.height() as u16;
 
-            Row::new(vec![
-                Cell::from(file.name.clone()),
-                Cell::from(if file.is_dir{"-".to_string()} else { format_file_size(file.size) }),
+            let mut cells = Vec::new();
+            if self.fs.git_status() {
+                cells.push(match self.fs.git_state(&file.name) {
+                    Some(state) => Cell::from(state.glyph()).style(Style::default().fg(git_color(state))),
+                    None => Cell::from(""),
+                });
+            }
+            cells.extend([
+                Cell::from(name),
+                // right aligned so the units line up
+                Cell::from(Line::from(if file.unreadable { "?".to_string() } else if file.is_dir{"-".to_string()} else { size_text(file.size) }).alignment(Alignment::Right)),
                 Cell::from(get_file_type(&file.path)),
-            ]).style(style)
-        }).collect();// [file_name, file_size, file_type] + style(for selected)
+            ]);
+            if self.show_permissions {
+                cells.push(Cell::from(if file.unreadable {
+                    format!("{}?????????", type_char(file.file_type))
+                } else {
+                    format_permissions(file.file_type, file.mode)
+                }));
+            }
+            if self.show_modified {
+                cells.push(Cell::from(match file.modified {
+                    Some(time) => format_modified(time, self.config.time_format),
+                    None => "-".to_string(),
+                }));
+            }
+            if self.show_owner && file.unreadable {
+                cells.extend([Cell::from("?"), Cell::from("?")]);
+            } else if self.show_owner {
+                cells.push(Cell::from(self.owner_names.user(file.uid)));
+                cells.push(Cell::from(self.owner_names.group(file.gid)));
+            }
+            (Row::new(cells).height(height).style(style), height)
+        }).unzip();// [(git), file_name, file_size, file_type, (permissions), (modified), (owner, group)] + style(for selected)
 
-        let mut title = self.fs.current_dir().display().to_string();
+        let mut title = format!("{} [Sort: {}]", self.fs.current_dir().display(), self.fs.sort_mode().label());
         if !self.search_query.is_empty() { // when searching, title should change
-            title = format!("{} [Searching: '{}']", title, self.search_query);
+            title = format!("{} [Searching: '{}', {}]", title, self.search_query, self.search_mode_label());
         }
 
-        let table = Table::new(rows, [Constraint::Min(30), Constraint::Length(12), Constraint::Min(6)])
-            .header(Row::new(vec!["Name", "Size", "Type"]).style(Style::default().add_modifier(Modifier::BOLD)))
-            .block(Block::default().borders(Borders::ALL).title(title))
-            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        let mut widths = Vec::new();
+        let mut header = Vec::new();
+        if self.fs.git_status() {
+            widths.push(Constraint::Length(1));
+            header.push(Line::from(""));
+        }
+        // arrow on the sorted column, size has its own, everything else is about the name
+        let mode = self.fs.sort_mode();
+        let arrow = if mode.ascending() != self.fs.sort_reversed() { "↑" } else { "↓" };
+        let (name_header, size_header) = if mode == SortMode::Size {
+            ("Name".to_string(), format!("Size {}", arrow))
+        } else {
+            (format!("Name {}", arrow), "Size".to_string())
+        };
+        widths.extend([Constraint::Min(30), Constraint::Length(size_width), Constraint::Min(6)]);
+        header.extend([Line::from(name_header), Line::from(size_header).alignment(Alignment::Right), Line::from("Type")]);
+        if self.show_permissions {
+            widths.push(Constraint::Length(PERMISSIONS_WIDTH));
+            header.push(Line::from("Mode"));
+        }
+        if self.show_modified {
+            widths.push(Constraint::Length(MODIFIED_WIDTH));
+            header.push(Line::from("Modified"));
+        }
+        if self.show_owner {
+            widths.extend([Constraint::Length(OWNER_WIDTH), Constraint::Length(OWNER_WIDTH)]);
+            header.extend([Line::from("Owner"), Line::from("Group")]);
+        }
+
+        let (border, highlight) = if active {
+            (Style::default(), Style::default().add_modifier(Modifier::REVERSED))
+        } else {
+            (Style::default().fg(Color::DarkGray), Style::default())
+        };
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL).border_style(border).title(title))
+            .row_highlight_style(highlight)
             .column_spacing(1);
 
         frame.render_stateful_widget(table, area, &mut self.table_state);
+        if active {
+            self.row_heights = heights;
+        }
+    }
+
+    fn render_preview(&mut self, frame: &mut Frame, area: Rect) {
+        let path = match self.get_cursor_file_info() {
+            Some((original_index, false)) => self.fs.files()[original_index].path.clone(),
+            _ => {
+                // nothing to show for dirs or an empty listing
+                frame.render_widget(Block::default().borders(Borders::ALL).title("Preview"), area);
+                return;
+            }
+        };
+
+        if self.preview.as_ref().is_none_or(|preview| preview.path != path) {
+            self.preview = Some(Preview::load(path, self.config.preview_bytes));
+            self.preview_scroll = 0;
+        }
+
+        match self.preview.as_ref().map(|preview| (&preview.bytes, preview.truncated)) {
+            Some((Ok(bytes), truncated)) if self.hex_preview || preview::is_binary(bytes) =>
+                self.preview_scroll = preview::render_hex_preview(frame, area, bytes, truncated, self.preview_scroll),
+            Some((Ok(bytes), truncated)) =>
+                self.preview_scroll = preview::render_text_preview(frame, area, bytes, truncated, self.preview_scroll),
+            Some((Err(error), _)) => {
+                let widget = Paragraph::new(format!("Cannot read: {}", error))
+                    .block(Block::default().borders(Borders::ALL).title("Preview"))
+                    .style(Style::default().fg(self.theme.error));
+                frame.render_widget(widget, area);
+            }
+            None => {}
+        }
     }
 
     fn render_status_bar(&mut self, frame: &mut Frame, area: Rect) {
         let (title, content, color) = match self.input_context {
             InputContext::Search =>
-                ("Search", Cow::Borrowed(self.input_buffer.as_str()), Color::Gray),
-            InputContext::ConfirmDelete =>
-                ("Confirm", Cow::Owned(format!("Removed files cannot recover (y/N): {}", self.input_buffer)), Color::Magenta),
+                (self.search_title(), Cow::Borrowed(self.input_buffer.as_str()), self.theme.search),
+            InputContext::NewFile =>
+                ("New File", Cow::Owned(self.new_entry_prompt()), self.theme.input),
+            InputContext::NewDir =>
+                ("New Directory", Cow::Owned(self.new_entry_prompt()), self.theme.input),
+            InputContext::NewDirEnter =>
+                ("New Directory and Enter", Cow::Owned(self.new_entry_prompt()), self.theme.input),
+            InputContext::Rename => {
+                let (preview, collides) = self.rename_preview();
+                if collides {
+                    ("Rename", Cow::Owned(format!("{}  [Exists]", preview)), self.theme.error)
+                } else {
+                    ("Rename", Cow::Owned(preview), self.theme.input)
+                }
+            }
+            InputContext::RecursiveSearch =>
+                ("Search Subtree", Cow::Borrowed(self.input_buffer.as_str()), self.theme.search),
+            InputContext::BatchRename =>
+                ("Rename Marked ({n} counter, {name}, {ext})", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::Shell =>
+                ("Shell (% = file, !cmd = interactive)", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::Command => match command::hint(&self.input_buffer) {
+                Some(hint) => ("Command", Cow::Owned(format!("{}  [{}]", self.input_buffer, hint)), self.theme.input),
+                None => ("Command", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            },
+            InputContext::GoTo =>
+                ("Go To", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::MarkGlob =>
+                ("Mark Matching (*.png, IMG_??.*)", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::ArchiveName =>
+                ("Pack Into (.tar.gz, .tar or .zip)", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::ExtFilter =>
+                ("Only Extension (empty clears)", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::OwnerFilter =>
+                ("Owner Filter (empty clears)", Cow::Borrowed(self.input_buffer.as_str()), self.theme.input),
+            InputContext::ConfirmEmptyDir | InputContext::ConfirmQuit | InputContext::ConfirmDelete =>
+                ("Confirm", Cow::Owned(format!("{}{}", self.confirm_text(), self.input_buffer)), self.theme.confirm),
+            InputContext::PasteConflict =>
+                ("Paste", Cow::Borrowed(self.confirm_message.as_str()), self.theme.confirm),
+            InputContext::FlattenConflict =>
+                ("Flatten", Cow::Borrowed(self.confirm_message.as_str()), self.theme.confirm),
             InputContext::None => {
-                let mut text = self.fs.status_info.clone();
-                if !self.search_query.is_empty() {
+                let icons = &self.config.status_icons;
+                let icon = match self.fs.status_flag {
+                    StatusFlag::Ready => &icons.ready,
+                    StatusFlag::Error => &icons.error,
+                    StatusFlag::Input => &icons.input,
+                    StatusFlag::Others => &icons.others,
+                };
+                let mut text = if icon.is_empty() {
+                    self.fs.status_info.clone()
+                } else {
+                    format!("{} {}", icon, self.fs.status_info)
+                };
+                if let Some(count) = self.count {
+                    text = format!("{} | Count: {}", text, count);
+                }
+                if self.keymap.is_pending() {
+                    text = format!("{} | Next Key…", text);
+                }
+                if self.fs.is_loading() {
+                    text = format!("{} | Loading… {} entries", text, self.fs.files().len());
+                }
+                if self.fs.marked_count() > 0 {
+                    text = format!("{} | {} Marked", text, self.fs.marked_count());
+                }
+                if !self.search_query.is_empty() && self.search_filters {
                     text = format!("{} | Search: '{}'", text, self.search_query);
+                } else if !self.search_query.is_empty() {
+                    let matches = self.search_matches();
+                    let cursor = self.table_state.selected();
+                    text = match matches.iter().position(|row| Some(*row) == cursor) {
+                        Some(position) => format!("{} | Search: '{}' (match {}/{})", text, self.search_query, position + 1, matches.len()),
+                        None => format!("{} | Search: '{}' ({} matches)", text, self.search_query, matches.len()),
+                    };
                 }
                 if self.show_hidden {
                     text = format!("{} | [Hidden Shown]", text);
                 }
+                if let Some(uid) = self.owner_filter {
+                    text = format!("{} | Owner: {}", text, self.owner_names.user(uid));
+                }
+                let (running, pending) = self.fs.queue().snapshot();
+                match (running, self.fs.queue().progress()) {
+                    (Some(label), Some((done, total))) if total > 0 => {
+                        let percent = (done as f64 / total as f64 * 100.0).min(100.0);
+                        text = format!("{} | Running: {} {:.0}% (Esc stops)", text, label, percent);
+                    }
+                    (Some(label), _) => text = format!("{} | Running: {}", text, label),
+                    _ => {}
+                }
+                if !pending.is_empty() {
+                    text = format!("{} (+{} queued)", text, pending.len());
+                }
+                if let Some(ext) = &self.ext_filter {
+                    text = format!("{} | Only: *.{}", text, ext);
+                }
+                match self.type_filter {
+                    TypeFilter::All => {}
+                    TypeFilter::DirsOnly => text = format!("{} | Only: Dirs", text),
+                    TypeFilter::FilesOnly => text = format!("{} | Only: Files", text),
+                }
+                if let Some((free, total)) = self.disk_space() {
+                    text = format!("{} | {} free of {}", text, format_file_size(free), format_file_size(total));
+                }
 
                 let color = match self.fs.status_flag {
-                    StatusFlag::Error => Color::Red,
-                    StatusFlag::Ready => Color::Green,
-                    StatusFlag::Input => Color::Yellow,
-                    _ => Color::White,
+                    StatusFlag::Error => self.theme.error,
+                    StatusFlag::Ready => self.theme.ready,
+                    StatusFlag::Input => self.theme.input,
+                    _ => self.theme.others,
                 };
                 ("Status", Cow::Owned(text), color)
             }
-            _ => ("Input", Cow::Borrowed(self.input_buffer.as_str()), Color::Yellow),
         };
 
         let widget = Paragraph::new(content.as_ref())
@@ -389,23 +2676,141 @@ impl App {
     ///
     /// # Helpers
     ///
+    // asked again after a cd, or once it is a few seconds old
+    fn disk_space(&mut self) -> Option<(u64, u64)> {
+        match &self.disk_space {
+            Some(cached) if cached.dir == *self.fs.current_dir() && cached.checked.elapsed() < DISK_SPACE_TTL => cached.space,
+            _ => {
+                let space = file_info::disk_space(self.fs.current_dir());
+                self.disk_space = Some(DiskSpace { dir: self.fs.current_dir().clone(), checked: Instant::now(), space });
+                space
+            }
+        }
+    }
+
+    fn confirm_text(&self) -> Cow<'_, str> {
+        match &self.confirm_size {
+            Some(usage) => {
+                let size = usage.total().map_or_else(|| "calculating…".to_string(), format_file_size);
+                Cow::Owned(self.confirm_message.replace("{size}", &size))
+            }
+            None => Cow::Borrowed(&self.confirm_message),
+        }
+    }
+
+    fn new_entry_prompt(&self) -> String {
+        // show where the entry will land, target dir is the selected dir if any
+        self.fs.target_dir().join(&self.input_buffer).display().to_string()
+    }
+
+    fn rename_preview(&self) -> (String, bool) { // (resulting path, collides)
+        let name = self.input_buffer.trim();
+        let source = self.get_cursor_file_info()
+            .map(|(original_index, _)| &self.fs.files()[original_index].path);
+        let target = match source {
+            Some(source) => source.with_file_name(name),
+            None => self.fs.current_dir().join(name),
+        };
+        // keeping the old name is not a collision
+        let collides = !name.is_empty() && target.exists() && source != Some(&target);
+        (target.display().to_string(), collides)
+    }
 
     fn filtered_files(&self) -> Vec<(usize, &FileInfo)> { // (original_index, file_info)
         // filter files, include hide and search
-        self.fs.files()
+        let mut scored: Vec<(i64, usize, &FileInfo)> = self.fs.files()
             .iter()
             .enumerate() // original index
-            .filter(|(_, file)| {
+            .filter_map(|(index, file)| match self.search_filters {
+                true => self.search_score(&file.name).map(|score| (score, index, file)),
+                false => Some((0, index, file)), // everything stays, n/N go through the matches
+            })
+            .filter(|(_, _, file)| {
                 // hide
                 let show_file = self.show_hidden || !file.name.starts_with('.');
-                // search
-                let matches_search = self.search_query.is_empty()
-                    || file.name.to_lowercase().contains(&self.search_query.to_lowercase());
-                show_file && matches_search
+                // owner
+                let owned = self.owner_filter.is_none_or(|uid| file.uid == uid);
+                // extension
+                let same_ext = self.ext_filter.as_ref().is_none_or(|ext| {
+                    !file.is_dir && file_system_info::extension_of(file).as_ref() == Some(ext)
+                });
+                let kind = match self.type_filter {
+                    TypeFilter::All => true,
+                    TypeFilter::DirsOnly => file.is_dir,
+                    TypeFilter::FilesOnly => !file.is_dir,
+                };
+                // .gitignore, only known inside a repo
+                let wanted = !self.fs.hide_ignored() || !self.fs.is_git_ignored(&file.name);
+                show_file && owned && same_ext && kind && wanted
             })
+            .collect();
+
+        // best fuzzy hits first, the sort is stable so ties keep the listing order
+        if self.fuzzy_search && self.search_filters && !self.search_query.is_empty() {
+            scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        }
+        scored.into_iter().map(|(_, index, file)| (index, file)).collect()
+    }
+
+    // rows of the listing that match the search, in listing order; none without a search
+    fn search_matches(&self) -> Vec<usize> {
+        if self.search_query.is_empty() {
+            return Vec::new();
+        }
+        self.filtered_files()
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, file))| self.search_score(&file.name).is_some())
+            .map(|(row, _)| row)
             .collect()
     }
 
+    // char indices of `name` the search matched, for highlighting; empty without a match
+    fn match_positions(&self, name: &str) -> Vec<usize> {
+        if self.search_query.is_empty() {
+            return Vec::new();
+        }
+        if self.fuzzy_search {
+            return fuzzy::positions(&self.search_query, name, self.case_sensitive).unwrap_or_default();
+        }
+        let same = |a: char, b: char| if self.case_sensitive { a == b } else { a.to_lowercase().eq(b.to_lowercase()) };
+        let name: Vec<char> = name.chars().collect();
+        let query: Vec<char> = self.search_query.chars().collect();
+        name.windows(query.len())
+            .position(|window| window.iter().zip(&query).all(|(a, b)| same(*a, *b)))
+            .map_or(Vec::new(), |start| (start..start + query.len()).collect())
+    }
+
+    // None when `name` does not match the search, every name matches an empty one
+    fn search_score(&self, name: &str) -> Option<i64> {
+        if self.search_query.is_empty() {
+            Some(0)
+        } else if self.fuzzy_search {
+            fuzzy::score(&self.search_query, name, self.case_sensitive)
+        } else if self.case_sensitive {
+            name.contains(&self.search_query).then_some(0)
+        } else {
+            name.to_lowercase().contains(&self.search_query.to_lowercase()).then_some(0)
+        }
+    }
+
+    fn search_mode_label(&self) -> String {
+        format!(
+            "{}, {}",
+            if self.fuzzy_search { "Fuzzy" } else { "Exact" },
+            if self.case_sensitive { "Case Sensitive" } else { "Ignore Case" }
+        )
+    }
+
+    fn search_title(&self) -> &'static str {
+        match (self.fuzzy_search, self.case_sensitive) {
+            (true, true) => "Search (Fuzzy, Case Sensitive)",
+            (true, false) => "Search (Fuzzy, Ignore Case)",
+            (false, true) => "Search (Exact, Case Sensitive)",
+            (false, false) => "Search (Exact, Ignore Case)",
+        }
+    }
+
     fn get_cursor_file_info(&self) -> Option<(usize, bool)> { // (original_index, is_dir)
         let filtered = self.filtered_files(); // (original_index, file_info)
         self.table_state.selected()
@@ -414,14 +2819,22 @@ impl App {
     }
 }
 
+fn hidden_marks_note(hidden: usize) -> String {
+    match hidden {
+        1 => "1 hidden mark ignored".to_string(),
+        _ => format!("{} hidden marks ignored", hidden),
+    }
+}
+
 fn format_file_size(size: u64) -> String {
     if size == 0 { return "0 B".to_string(); }
 
-    let units = ["B", "KB", "MB", "GB"];
+    let units = ["B", "KB", "MB", "GB", "TB", "PB", "EB"];
     let mut value = size as f64;
     let mut unit_idx = 0;
 
-    while value >= 1024.0 && unit_idx < units.len() - 1 {
+    // anything that would print as "1024.0" goes up a unit
+    while value >= 1023.95 && unit_idx < units.len() - 1 {
         value /= 1024.0;
         unit_idx += 1;
     }
@@ -429,19 +2842,385 @@ fn format_file_size(size: u64) -> String {
     format!("{:.1} {}", value, units[unit_idx])
 }
 
+// "2h ago" or "2024-05-01 13:37"; times in the future are always absolute
+fn format_modified(time: SystemTime, format: TimeFormat) -> String {
+    if let (TimeFormat::Relative, Ok(age)) = (format, SystemTime::now().duration_since(time)) {
+        let seconds = age.as_secs();
+        return match seconds {
+            0..60 => "just now".to_string(),
+            60..3_600 => format!("{}m ago", seconds / 60),
+            3_600..86_400 => format!("{}h ago", seconds / 3_600),
+            86_400..2_592_000 => format!("{}d ago", seconds / 86_400),
+            2_592_000..31_536_000 => format!("{}mo ago", seconds / 2_592_000),
+            _ => format!("{}y ago", seconds / 31_536_000),
+        };
+    }
+    let (year, month, day, hour, minute, _) = file_info::local_time(time);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, hour, minute)
+}
+
+// long names take a second line instead of being cut, anything beyond that gets an ellipsis
+fn wrap_name(name: &str, width: usize, matched: &[usize], match_style: Style) -> Text<'static> {
+    let chars: Vec<char> = name.chars().collect();
+    if width < 2 || chars.len() <= width {
+        return Text::from(highlight(&chars, 0, matched, match_style));
+    }
+
+    let (first, rest) = chars.split_at(width);
+    let second: Vec<char> = if rest.len() > width {
+        rest[..width - 1].iter().copied().chain(std::iter::once('…')).collect()
+    } else {
+        rest.to_vec()
+    };
+    Text::from(vec![highlight(first, 0, matched, match_style), highlight(&second, width, matched, match_style)])
+}
+
+// `chars` start at char `offset` of the name, runs of matched ones get `match_style` on top of the row's
+fn highlight(chars: &[char], offset: usize, matched: &[usize], match_style: Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (index, c) in chars.iter().enumerate() {
+        let is_matched = matched.contains(&(offset + index));
+        if is_matched != run_matched && !run.is_empty() {
+            let text = std::mem::take(&mut run);
+            spans.push(if run_matched { Span::styled(text, match_style) } else { Span::raw(text) });
+        }
+        run_matched = is_matched;
+        run.push(*c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_matched { Span::styled(run, match_style) } else { Span::raw(run) });
+    }
+    Line::from(spans)
+}
+
+// "a/b" -> "a/c/d" gives "../c/d"
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+// 1234567 -> "1,234,567 B"
+fn format_bytes_exact(size: u64) -> String {
+    let digits = size.to_string();
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3 + 2);
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+
+    text.push_str(" B");
+    text
+}
+
+// ls-like: what kind of entry it is, at a glance
+fn file_style(file: &FileInfo, theme: &Theme) -> Style {
+    let color = if file.broken_link {
+        theme.error
+    } else if file.link_target.is_some() {
+        theme.symlink
+    } else if file.is_dir {
+        theme.directory
+    } else if file.file_type.is_file() && file.mode & 0o111 != 0 {
+        theme.executable
+    } else if file_system_info::extension_of(file).is_some_and(|ext| ARCHIVES.contains(&ext.as_str())) {
+        theme.archive
+    } else {
+        return Style::default();
+    };
+    Style::default().fg(color)
+}
+
+fn git_color(state: GitState) -> Color {
+    match state {
+        GitState::Modified => Color::Yellow,
+        GitState::Staged => Color::Green,
+        GitState::Untracked => Color::Red,
+        GitState::Ignored => Color::DarkGray,
+    }
+}
+
 fn get_file_type(path: &PathBuf) -> &'static str {
     if let Ok(metadata) = metadata(path) {
-        let file_type = metadata.file_type();
-
-        if file_type.is_dir() { "DIR" }
-        else if file_type.is_file() { "FILE" }
-        else if file_type.is_symlink() { "SYMLINK" }
-        else if file_type.is_fifo() { "FIFO" }
-        else if file_type.is_char_device() { "CHAR" }
-        else if file_type.is_block_device() { "BLOCK" }
-        else if file_type.is_socket() { "SOCKET" }
-        else { "UNKNOWN" }
+        file_type_name(metadata.file_type())
     } else {
         "ERROR"
     }
 }
+
+fn file_type_name(file_type: FileType) -> &'static str {
+    if file_type.is_dir() { "DIR" }
+    else if file_type.is_file() { "FILE" }
+    else if file_type.is_symlink() { "SYMLINK" }
+    else if file_type.is_fifo() { "FIFO" }
+    else if file_type.is_char_device() { "CHAR" }
+    else if file_type.is_block_device() { "BLOCK" }
+    else if file_type.is_socket() { "SOCKET" }
+    else { "UNKNOWN" }
+}
+
+// leading letter of `ls -l`
+fn type_char(file_type: FileType) -> char {
+    match file_type_name(file_type) {
+        "DIR" => 'd',
+        "SYMLINK" => 'l',
+        "FIFO" => 'p',
+        "CHAR" => 'c',
+        "BLOCK" => 'b',
+        "SOCKET" => 's',
+        _ => '-',
+    }
+}
+
+// "drwxr-xr-x", setuid/setgid/sticky shown in the execute slots like `ls`
+fn format_permissions(file_type: FileType, mode: u32) -> String {
+    let mut text = String::from(type_char(file_type));
+    // (read, write, execute, special bit, special letter) for user, group, other
+    for (shift, special, letter) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => letter,
+            (false, true) => letter.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_info::test_dir::TestDir;
+
+    fn test_app(dir: &TestDir) -> App {
+        App::new(dir.path().to_path_buf(), Config::default(), Keymap::default(), Theme::default()).unwrap()
+    }
+
+    fn index_of(app: &App, name: &str) -> usize {
+        app.fs.files().iter().position(|file| file.name == name).unwrap()
+    }
+
+    // until the queue is idle and its results were taken in
+    fn wait_for_jobs(app: &mut App) {
+        for _ in 0..500 {
+            if app.fs.poll_jobs().unwrap() && !app.fs.queue().is_busy() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("queue did not finish");
+    }
+
+    #[test]
+    fn file_size_changes_unit_at_1024() {
+        assert_eq!(format_file_size(0), "0 B");
+        assert_eq!(format_file_size(1023), "1023.0 B");
+        assert_eq!(format_file_size(1024), "1.0 KB");
+        assert_eq!(format_file_size(1024 * 1024 - 1), "1.0 MB");
+        assert_eq!(format_file_size(1024 * 1024), "1.0 MB");
+        assert_eq!(format_file_size(1024 * 1024 + 1), "1.0 MB");
+        assert_eq!(format_file_size(1024u64.pow(4) - 1), "1.0 TB");
+        assert_eq!(format_file_size(1024u64.pow(4) - 1024u64.pow(3) / 10), "1023.9 GB");
+        assert_eq!(format_file_size(1024u64.pow(4)), "1.0 TB");
+        assert_eq!(format_file_size(u64::MAX), "16.0 EB");
+    }
+
+    #[test]
+    fn j_moves_down_k_moves_up_and_both_wrap() {
+        let dir = TestDir::new();
+        for name in ["a", "b", "c"] {
+            dir.file(name, "");
+        }
+        let mut app = test_app(&dir);
+        let press = |app: &mut App, key: char| app.handle_key(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE)).unwrap();
+        app.table_state.select(Some(0));
+
+        press(&mut app, 'j');
+        assert_eq!(app.table_state.selected(), Some(1));
+        press(&mut app, 'k');
+        assert_eq!(app.table_state.selected(), Some(0));
+        press(&mut app, 'k');
+        assert_eq!(app.table_state.selected(), Some(2));
+        press(&mut app, 'j');
+        assert_eq!(app.table_state.selected(), Some(0));
+        // a count stops at the end instead
+        press(&mut app, '5');
+        press(&mut app, 'j');
+        assert_eq!(app.table_state.selected(), Some(2));
+    }
+
+    #[test]
+    fn new_file_takes_names_separated_by_spaces() {
+        let dir = TestDir::new();
+        let mut app = test_app(&dir);
+
+        app.run_action(Action::NewFile, None).unwrap();
+        for key in "a.txt b.txt,  c.txt".chars() {
+            app.handle_input_mode(KeyCode::Char(key)).unwrap();
+        }
+        app.handle_input_mode(KeyCode::Enter).unwrap();
+
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(dir.path().join(name).is_file(), "{}", name);
+        }
+        assert_eq!(app.fs.files().len(), 3);
+    }
+
+    #[test]
+    fn delete_leaves_marks_hidden_by_search() {
+        let dir = TestDir::new();
+        dir.file("keep.txt", "");
+        dir.file("drop.log", "");
+        let mut app = test_app(&dir);
+        app.mark_listed(false).unwrap();
+        app.search_query = "drop".to_string();
+
+        app.start_delete_confirm().unwrap();
+        app.handle_input_mode(KeyCode::Char('y')).unwrap();
+        app.handle_input_mode(KeyCode::Enter).unwrap();
+        assert!(app.fs.status_info().ends_with("1 hidden mark ignored"));
+        wait_for_jobs(&mut app);
+
+        assert!(!dir.path().join("drop.log").exists());
+        assert!(dir.path().join("keep.txt").exists());
+        assert!(app.fs.is_marked(index_of(&app, "keep.txt")));
+    }
+
+    #[test]
+    fn copy_then_paste_into_another_dir() {
+        let dir = TestDir::new();
+        dir.file("a.txt", "a");
+        dir.file("sub/old.txt", "");
+        let mut app = test_app(&dir);
+
+        app.select("a.txt");
+        app.run_action(Action::Copy, None).unwrap();
+        app.select("sub");
+        app.run_action(Action::Enter, None).unwrap();
+        app.run_action(Action::Paste, None).unwrap();
+        wait_for_jobs(&mut app);
+
+        assert_eq!(std::fs::read_to_string(dir.path().join("sub/a.txt")).unwrap(), "a");
+        assert!(dir.path().join("a.txt").exists());
+        // the cursor still points into the listing it is in
+        let (index, _) = app.get_cursor_file_info().unwrap();
+        assert!(app.fs.files()[index].path.starts_with(dir.path().join("sub")));
+    }
+
+    #[test]
+    fn new_file_in_an_empty_dir_gets_the_cursor() {
+        let dir = TestDir::new();
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+        let mut app = test_app(&dir);
+
+        app.select("empty");
+        app.run_action(Action::Enter, None).unwrap();
+        assert_eq!(app.table_state.selected(), None);
+        app.run_action(Action::NewFile, None).unwrap();
+        for key in "new.txt".chars() {
+            app.handle_input_mode(KeyCode::Char(key)).unwrap();
+        }
+        app.handle_input_mode(KeyCode::Enter).unwrap();
+
+        let (index, _) = app.get_cursor_file_info().unwrap();
+        assert_eq!(app.fs.files()[index].name, "new.txt");
+    }
+
+    #[test]
+    fn follow_symlink_to_a_hidden_file_marks_nothing() {
+        let dir = TestDir::new();
+        dir.file("sub/visible.txt", "");
+        dir.file("sub/.secret", "");
+        std::os::unix::fs::symlink("sub/.secret", dir.path().join("link")).unwrap();
+        let mut app = test_app(&dir);
+        app.show_hidden = false;
+
+        app.select("link");
+        app.follow_symlink().unwrap();
+        assert_eq!(app.fs.current_dir(), &dir.path().join("sub"));
+        assert_eq!(app.fs.marked_count(), 0);
+        assert_eq!(app.fs.status_info(), ".secret Is Hidden or Filtered Out");
+
+        app.show_hidden = true;
+        app.go_parent_dir().unwrap();
+        app.select("link");
+        app.follow_symlink().unwrap();
+        assert!(app.fs.is_marked(index_of(&app, ".secret")));
+    }
+
+    #[test]
+    fn empty_dir_without_trash_deletes_for_good() {
+        let dir = TestDir::new();
+        dir.file("a.o", "");
+        dir.file("obj/b.o", "");
+        let mut app = test_app(&dir);
+
+        app.run_action(Action::EmptyDir, None).unwrap();
+        assert!(app.confirm_message.starts_with("PERMANENTLY delete ALL 2 entries"));
+        for key in "yes".chars() {
+            app.handle_input_mode(KeyCode::Char(key)).unwrap();
+        }
+        app.handle_input_mode(KeyCode::Enter).unwrap();
+        wait_for_jobs(&mut app);
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn subtree_search_lists_relative_paths_in_the_table() {
+        let dir = TestDir::new();
+        dir.file("top.rs", "");
+        dir.file("src/deep/main.rs", "");
+        dir.file("src/notes.txt", "");
+        let mut app = test_app(&dir);
+        app.fuzzy_search = false;
+
+        app.start_finder(".rs".to_string());
+        for _ in 0..500 {
+            app.update_finder();
+            if app.finder.is_none() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let mut names: Vec<&str> = app.filtered_files().iter().map(|(_, file)| file.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["src/deep/main.rs", "top.rs"]);
+
+        // Enter goes to where the result lives
+        app.select("src/deep/main.rs");
+        app.enter_current().unwrap();
+        assert_eq!(app.fs.current_dir(), &dir.path().join("src/deep"));
+        assert!(!app.fs.is_showing_found());
+        let (index, _) = app.get_cursor_file_info().unwrap();
+        assert_eq!(app.fs.files()[index].name, "main.rs");
+
+        // Esc gives the plain listing back
+        app.go_parent_dir().unwrap();
+        app.go_parent_dir().unwrap();
+        app.start_finder(".rs".to_string());
+        app.handle_esc().unwrap();
+        assert!(!app.fs.is_showing_found() && app.finder.is_none());
+        assert_eq!(app.fs.files().len(), 2);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+static CHUNK: usize = 256 * 1024;
+
+// (sha256, md5) as lowercase hex, or why the file could not be read
+type Sums = Result<(String, String), String>;
+
+// hashes a file on its own thread a chunk at a time, a big image never sits in memory whole
+pub struct Checksum {
+    pub path: PathBuf,
+    pub size: u64,
+    read: Arc<AtomicU64>,
+    sums: Arc<Mutex<Option<Sums>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Checksum {
+    pub fn start(path: PathBuf, size: u64) -> Self {
+        let read = Arc::new(AtomicU64::new(0));
+        let sums = Arc::new(Mutex::new(None));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let (hash_path, hash_read, hash_sums, hash_cancel) = (path.clone(), Arc::clone(&read), Arc::clone(&sums), Arc::clone(&cancel));
+        thread::spawn(move || {
+            if let Some(result) = hash(&hash_path, &hash_read, &hash_cancel) {
+                *hash_sums.lock().unwrap() = Some(result);
+            }
+        });
+
+        Checksum { path, size, read, sums, cancel }
+    }
+
+    // bytes hashed so far
+    pub fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    pub fn sums(&self) -> Option<Sums> {
+        self.sums.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Checksum {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// None when cancelled
+fn hash(path: &Path, read: &AtomicU64, cancel: &AtomicBool) -> Option<Sums> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) => return Some(Err(error.to_string())),
+    };
+    let (mut sha256, mut md5) = (Sha256::new(), Md5::new());
+    let mut buffer = vec![0; CHUNK];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let count = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Some(Err(error.to_string())),
+        };
+        sha256.update(&buffer[..count]);
+        md5.update(&buffer[..count]);
+        read.fetch_add(count as u64, Ordering::Relaxed);
+    }
+    Some(Ok((hex(&sha256.finalize()), hex(&md5.finalize()))))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
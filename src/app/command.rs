@@ -0,0 +1,65 @@
+use anyhow::{anyhow, Result};
+use crate::fs_info::file_system_info::SortMode;
+use crate::keys::{self, Action};
+
+// one line typed after ":"
+pub enum Command {
+    Action(Action), // any keymap action by its name, ":toggle_hidden"
+    Sort(SortMode),
+    Mkdir(String),
+    Touch(String),
+    Rename(String),
+    GoTo(String),
+    Search(String), // empty clears
+    Shell(String),
+}
+
+// offered while the name is typed; every action name works too
+static COMMANDS: &[&str] = &[
+    "sort", "reverse", "mkdir", "touch", "rename", "delete", "goto", "search", "shell", "hidden", "undo", "redo", "quit",
+];
+
+// "name args", ":!cmd" is short for ":shell cmd"
+pub fn parse(line: &str) -> Result<Command> {
+    if let Some(command) = line.strip_prefix('!') {
+        return Ok(Command::Shell(command.trim().to_string()));
+    }
+    let (name, args) = match line.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (line, ""),
+    };
+    let required = || match args {
+        "" => Err(anyhow!("{} Needs an Argument", name)),
+        _ => Ok(args.to_string()),
+    };
+
+    Ok(match name {
+        "sort" if args.is_empty() => Command::Action(Action::CycleSort),
+        "sort" => Command::Sort(SortMode::from_name(args).ok_or_else(|| anyhow!("Unknown Sort: {}", args))?),
+        "reverse" => Command::Action(Action::ReverseSort),
+        "mkdir" => Command::Mkdir(required()?),
+        "touch" => Command::Touch(required()?),
+        "rename" => Command::Rename(required()?),
+        "delete" | "rm" => Command::Action(Action::Delete),
+        "goto" | "cd" if args.is_empty() => Command::GoTo("~".to_string()),
+        "goto" | "cd" => Command::GoTo(args.to_string()),
+        "search" => Command::Search(args.to_string()),
+        "shell" => Command::Shell(required()?),
+        "hidden" => Command::Action(Action::ToggleHidden),
+        "q" | "quit" => Command::Action(Action::Quit),
+        _ => match keys::parse_action(name) {
+            Some(action) if args.is_empty() => Command::Action(action),
+            Some(_) => return Err(anyhow!("{} Takes No Argument", name)),
+            None => return Err(anyhow!("Unknown Command: {}", name)),
+        },
+    })
+}
+
+// the commands a half typed name could become, None once the arguments start
+pub fn hint(line: &str) -> Option<String> {
+    if line.contains(char::is_whitespace) || line.starts_with('!') {
+        return None;
+    }
+    let matches: Vec<&str> = COMMANDS.iter().copied().filter(|name| name.starts_with(line)).collect();
+    (!matches.is_empty()).then(|| matches.join(", "))
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// adds up what a set of entries takes on its own thread, a big tree never stalls the prompt asking about it
+pub struct DiskUsage {
+    total: Arc<Mutex<Option<u64>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DiskUsage {
+    pub fn start(paths: Vec<PathBuf>) -> Self {
+        let total = Arc::new(Mutex::new(None));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let (sum_total, sum_cancel) = (Arc::clone(&total), Arc::clone(&cancel));
+        thread::spawn(move || {
+            let sum = paths.iter().map(|path| usage(path, &sum_cancel)).sum();
+            if !sum_cancel.load(Ordering::Relaxed) {
+                *sum_total.lock().unwrap() = Some(sum);
+            }
+        });
+
+        DiskUsage { total, cancel }
+    }
+
+    // None while still counting
+    pub fn total(&self) -> Option<u64> {
+        *self.total.lock().unwrap()
+    }
+}
+
+impl Drop for DiskUsage {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// links count as themselves, what they point at is not removed with them
+fn usage(path: &Path, cancel: &AtomicBool) -> u64 {
+    if cancel.load(Ordering::Relaxed) {
+        return 0;
+    }
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| usage(&entry.path(), cancel)).sum())
+        .unwrap_or(0)
+}
@@ -0,0 +1,217 @@
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+// things that need the real terminal, run by the main loop with the TUI torn down
+pub enum External {
+    FuzzyPicker,
+    Editor(PathBuf),
+    OpenWith(String, PathBuf), // (command, file)
+    Shell(String), // typed command, gets the terminal
+    Subshell(PathBuf), // interactive $SHELL started in this dir
+}
+
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    run_attached(&editor, path)
+}
+
+// commands may carry arguments ("code -w"), so let the shell split them
+pub fn run_attached(command: &str, path: &Path) -> Result<()> {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", command))
+        .arg("sh")
+        .arg(path)
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", command, status));
+    }
+    Ok(())
+}
+
+// GUI apps outlive the shell that starts them, so there is nothing left for us to wait on
+pub fn spawn_detached(command: &str, path: &Path) -> Result<()> {
+    // once backgrounded a missing program goes unnoticed, check up front
+    let program = command.split_whitespace().next().unwrap_or_default();
+    let found = Command::new("sh")
+        .arg("-c")
+        .arg("command -v \"$1\" >/dev/null")
+        .arg("sh")
+        .arg(program)
+        .status()?
+        .success();
+    if !found {
+        return Err(anyhow!("not found: {}", program));
+    }
+
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("nohup {} \"$1\" >/dev/null 2>&1 &", command))
+        .arg("sh")
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(())
+}
+
+// interactive commands own the terminal; wait for Enter so their output can be read
+pub fn run_interactive(command: &str) -> Result<Option<i32>> {
+    let status = Command::new("sh").arg("-c").arg(command).status()?;
+    print!("\n[exit {}] press Enter to continue", status.code().map_or("signal".to_string(), |code| code.to_string()));
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(status.code())
+}
+
+// $SHELL, or sh, until the user exits it; $NPNS_LEVEL tells a prompt it runs inside one of us
+pub fn open_shell(dir: &Path) -> Result<Option<i32>> {
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".to_string());
+    let level = std::env::var("NPNS_LEVEL").ok().and_then(|level| level.parse::<u32>().ok()).unwrap_or(0);
+    let status = Command::new(&shell)
+        .current_dir(dir)
+        .env("NPNS_LEVEL", (level + 1).to_string())
+        .status()
+        .map_err(|error| anyhow!("{}: {}", shell, error))?;
+    Ok(status.code())
+}
+
+// runs without a terminal on its own thread, the UI keeps going while it does
+pub struct Captured {
+    pub command: String,
+    result: Receiver<Result<(bool, String), String>>,
+}
+
+impl Captured {
+    pub fn start(command: String) -> Self {
+        let (sender, result) = mpsc::channel();
+        let run_command = command.clone();
+        thread::spawn(move || {
+            let _ = sender.send(run_captured(&run_command).map_err(|error| error.to_string()));
+        });
+        Captured { command, result }
+    }
+
+    // None while it is still running; (success, summary) once it is done
+    pub fn result(&self) -> Option<Result<(bool, String), String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("Command thread died".to_string())),
+        }
+    }
+}
+
+// sums the output up in one line: the last line of output, or the exit code
+fn run_captured(command: &str) -> Result<(bool, String)> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()?;
+
+    // on failure the reason is usually on stderr
+    let streams = if output.status.success() { [&output.stdout, &output.stderr] } else { [&output.stderr, &output.stdout] };
+    let last_line = streams.iter()
+        .find_map(|stream| String::from_utf8_lossy(stream).lines().rev().find(|line| !line.trim().is_empty()).map(str::to_string));
+    let summary = match (last_line, output.status.code()) {
+        (Some(line), Some(0)) => line,
+        (Some(line), Some(code)) => format!("{} (exit code {})", line, code),
+        (None, Some(code)) => format!("exit code {}", code),
+        (_, None) => "killed by signal".to_string(),
+    };
+    Ok((output.status.success(), summary))
+}
+
+// "%" becomes the quoted path, "%%" a literal "%"
+pub fn substitute_path(command: &str, path: Option<&Path>) -> String {
+    let quoted = path.map(|path| format!("'{}'", path.to_string_lossy().replace('\'', "'\\''")));
+    let mut result = String::new();
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('%', Some('%')) => {
+                chars.next();
+                result.push('%');
+            }
+            ('%', _) => result.push_str(quoted.as_deref().unwrap_or("''")),
+            (c, _) => result.push(c),
+        }
+    }
+    result
+}
+
+// first clipboard tool that is installed and works (wayland, X11, macOS)
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let tools: [&[&str]; 4] = [
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+        &["pbcopy"],
+    ];
+
+    for tool in tools {
+        let mut child = match Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        // e.g. wl-copy outside of a wayland session, try the next one
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("no clipboard tool (wl-copy, xclip, xsel, pbcopy)"))
+}
+
+// feeds `items` to the picker on stdin and returns the chosen lines, None when cancelled
+pub fn fuzzy_pick(command: &str, items: &[String]) -> Result<Option<Vec<String>>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // the picker may quit before reading everything, that is not an error
+        let _ = stdin.write_all(items.join("\n").as_bytes());
+    }
+    let output = child.wait_with_output()?;
+
+    match output.status.code() {
+        Some(0) => {
+            let chosen = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect();
+            Ok(Some(chosen))
+        }
+        // sh reports a missing command as 127
+        Some(127) => Err(anyhow!("not installed: {}", command)),
+        // fzf: 1 no match, 130 aborted
+        _ => Ok(None),
+    }
+}
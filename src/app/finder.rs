@@ -0,0 +1,101 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use crate::app::fuzzy;
+
+static MAX_RESULTS: usize = 1000;
+
+// how names are matched, the same modes as the search in the listing
+#[derive(Clone, Copy)]
+pub struct MatchMode {
+    pub fuzzy: bool,
+    pub case_sensitive: bool,
+    pub show_hidden: bool,
+}
+
+// walks the tree below `root` on its own thread, results pile up while the UI keeps drawing
+pub struct Finder {
+    pub query: String,
+    pub root: PathBuf,
+    found: Arc<Mutex<Vec<PathBuf>>>,
+    done: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Finder {
+    pub fn start(root: PathBuf, query: String, max_depth: usize, mode: MatchMode) -> Self {
+        let found = Arc::new(Mutex::new(Vec::new()));
+        let done = Arc::new(AtomicBool::new(false));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let (walk_root, walk_query) = (root.clone(), query.clone());
+        let (walk_found, walk_done, walk_cancel) = (Arc::clone(&found), Arc::clone(&done), Arc::clone(&cancel));
+        thread::spawn(move || {
+            walk(&walk_root, &walk_query, max_depth, mode, &walk_found, &walk_cancel);
+            walk_done.store(true, Ordering::Relaxed);
+        });
+
+        Finder { query, root, found, done, cancel }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    // what turned up past the first `seen`, results are only ever added to the end
+    pub fn found_since(&self, seen: usize) -> Vec<PathBuf> {
+        self.found.lock().unwrap().get(seen..).map(<[PathBuf]>::to_vec).unwrap_or_default()
+    }
+}
+
+// the walk stops with the results it feeds
+impl Drop for Finder {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// breadth first, so shallow matches show up first; linked dirs are not entered, they could loop
+fn walk(root: &Path, query: &str, max_depth: usize, mode: MatchMode, found: &Mutex<Vec<PathBuf>>, cancel: &AtomicBool) {
+    let mut pending = VecDeque::from([(root.to_path_buf(), 0)]);
+    while let Some((dir, depth)) = pending.pop_front() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue, // unreadable dirs are skipped, not fatal
+        };
+        for entry in entries.flatten() {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !mode.show_hidden && name.starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            if matches(query, &name, mode) {
+                let mut found = found.lock().unwrap();
+                found.push(path.clone());
+                if found.len() >= MAX_RESULTS {
+                    return;
+                }
+            }
+            let is_dir = entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false);
+            if is_dir && depth + 1 < max_depth {
+                pending.push_back((path, depth + 1));
+            }
+        }
+    }
+}
+
+fn matches(query: &str, name: &str, mode: MatchMode) -> bool {
+    if mode.fuzzy {
+        fuzzy::score(query, name, mode.case_sensitive).is_some()
+    } else if mode.case_sensitive {
+        name.contains(query)
+    } else {
+        name.to_lowercase().contains(&query.to_lowercase())
+    }
+}
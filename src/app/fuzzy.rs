@@ -0,0 +1,47 @@
+// fzf-like subsequence match: "mfs" hits "my_file_system.rs".
+// None when the query letters do not all appear in order, otherwise higher is better.
+pub fn score(query: &str, name: &str, case_sensitive: bool) -> Option<i64> {
+    let found = positions(query, name, case_sensitive)?;
+    let name: Vec<char> = name.chars().collect();
+
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &index in &found {
+        score += 1;
+        if previous == Some(index.wrapping_sub(1)) {
+            score += 8; // runs of letters beat scattered ones
+        } else if let Some(previous) = previous {
+            score -= (index - previous - 1).min(8) as i64; // small penalty for the gap
+        }
+        if is_word_start(&name, index) {
+            score += 6;
+        }
+        previous = Some(index);
+    }
+    // between equal matches the shorter name is the closer one
+    Some(score * 16 - name.len().min(15) as i64)
+}
+
+// char index in `name` of each query letter, first fit from the left
+pub fn positions(query: &str, name: &str, case_sensitive: bool) -> Option<Vec<usize>> {
+    let fold = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+    let name: Vec<char> = name.chars().collect();
+
+    let mut found = Vec::new();
+    let mut position = 0;
+    for wanted in query.chars().map(fold) {
+        let index = (position..name.len()).find(|&i| fold(name[i]) == wanted)?;
+        found.push(index);
+        position = index + 1;
+    }
+    Some(found)
+}
+
+// start of the name, after a separator, or a lower to upper case step ("fileSystem")
+fn is_word_start(name: &[char], index: usize) -> bool {
+    match index.checked_sub(1).map(|previous| name[previous]) {
+        None => true,
+        Some(previous) => matches!(previous, '_' | '-' | '.' | ' ')
+            || (previous.is_lowercase() && name[index].is_uppercase()),
+    }
+}
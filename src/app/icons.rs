@@ -0,0 +1,92 @@
+use crate::fs_info::file_info::FileInfo;
+use crate::fs_info::file_system_info::extension_of;
+
+// Nerd Font glyphs, a terminal without one shows boxes, hence `icons = false` by default
+static DIR: &str = "\u{f07b}";
+static LINK: &str = "\u{f0c1}";
+static FILE: &str = "\u{f15b}";
+
+// whole names first, they say more than the extension
+static NAMES: &[(&str, &str)] = &[
+    ("Makefile", "\u{e779}"),
+    ("Dockerfile", "\u{f308}"),
+    ("Cargo.toml", "\u{e7a8}"),
+    ("Cargo.lock", "\u{e7a8}"),
+    (".gitignore", "\u{e702}"),
+    ("LICENSE", "\u{f0219}"),
+];
+
+static EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "\u{e7a8}"),
+    ("py", "\u{e73c}"),
+    ("md", "\u{e73e}"),
+    ("c", "\u{e61e}"),
+    ("h", "\u{e61e}"),
+    ("cpp", "\u{e61d}"),
+    ("hpp", "\u{e61d}"),
+    ("go", "\u{e626}"),
+    ("java", "\u{e738}"),
+    ("js", "\u{e74e}"),
+    ("ts", "\u{e628}"),
+    ("lua", "\u{e620}"),
+    ("sh", "\u{f489}"),
+    ("bash", "\u{f489}"),
+    ("vim", "\u{e62b}"),
+    ("html", "\u{e736}"),
+    ("css", "\u{e749}"),
+    ("json", "\u{e60b}"),
+    ("toml", "\u{e615}"),
+    ("yaml", "\u{e615}"),
+    ("yml", "\u{e615}"),
+    ("ini", "\u{e615}"),
+    ("conf", "\u{e615}"),
+    ("dts", "\u{f2db}"),
+    ("txt", "\u{f15c}"),
+    ("log", "\u{f15c}"),
+    ("pdf", "\u{f1c1}"),
+    ("png", "\u{f1c5}"),
+    ("jpg", "\u{f1c5}"),
+    ("jpeg", "\u{f1c5}"),
+    ("gif", "\u{f1c5}"),
+    ("bmp", "\u{f1c5}"),
+    ("svg", "\u{f1c5}"),
+    ("webp", "\u{f1c5}"),
+    ("mp3", "\u{f1c7}"),
+    ("flac", "\u{f1c7}"),
+    ("wav", "\u{f1c7}"),
+    ("ogg", "\u{f1c7}"),
+    ("mp4", "\u{f1c8}"),
+    ("mkv", "\u{f1c8}"),
+    ("avi", "\u{f1c8}"),
+    ("webm", "\u{f1c8}"),
+    ("zip", "\u{f410}"),
+    ("tar", "\u{f410}"),
+    ("gz", "\u{f410}"),
+    ("xz", "\u{f410}"),
+    ("bz2", "\u{f410}"),
+    ("zst", "\u{f410}"),
+    ("7z", "\u{f410}"),
+    ("rar", "\u{f410}"),
+    ("img", "\u{f0a0}"),
+    ("iso", "\u{f0a0}"),
+    ("bin", "\u{f471}"),
+    ("elf", "\u{f471}"),
+    ("so", "\u{f471}"),
+    ("o", "\u{f471}"),
+    ("lock", "\u{f023}"),
+];
+
+pub fn icon(file: &FileInfo) -> &'static str {
+    if file.is_dir {
+        return DIR;
+    }
+    if file.link_target.is_some() {
+        return LINK;
+    }
+    if let Some((_, glyph)) = NAMES.iter().find(|(name, _)| *name == file.name) {
+        return glyph;
+    }
+    extension_of(file)
+        .and_then(|ext| EXTENSIONS.iter().find(|(known, _)| *known == ext))
+        .map_or(FILE, |(_, glyph)| glyph)
+}
@@ -0,0 +1,107 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum PickerKind {
+    Clipboard,
+    RecentFiles,
+    RecentDirs,
+    OpenWith,
+    Bookmarks,
+    Queue,
+    Checksum, // sums of one file, shown once they are done
+}
+
+// popup list, the caller decides what picking an item means from `kind`
+pub struct Picker {
+    pub kind: PickerKind,
+    title: String,
+    items: Vec<String>,
+    state: ListState,
+}
+
+impl Picker {
+    pub fn new(kind: PickerKind, title: &str, items: Vec<String>) -> Self {
+        let mut state = ListState::default();
+        state.select(if items.is_empty() { None } else { Some(0) });
+        Picker {
+            kind,
+            title: title.to_string(),
+            items,
+            state,
+        }
+    }
+
+    pub fn move_cursor(&mut self, delta: i32) {
+        let len = self.items.len();
+        if len == 0 {
+            return;
+        }
+
+        let new_index = match self.state.selected() {
+            Some(i) => {
+                if delta > 0 {
+                    if i >= len - 1 { 0 } else { i + 1 }
+                } else {
+                    if i == 0 { len - 1 } else { i - 1 }
+                }
+            },
+            None => 0,
+        };
+        self.state.select(Some(new_index));
+    }
+
+    // new contents for an open picker, the cursor stays where it was if it still fits
+    pub fn update(&mut self, title: &str, items: Vec<String>) {
+        self.title = title.to_string();
+        let selected = match self.state.selected() {
+            _ if items.is_empty() => None,
+            Some(i) => Some(i.min(items.len() - 1)),
+            None => Some(0),
+        };
+        self.items = items;
+        self.state.select(selected);
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+
+    pub fn render(&mut self, frame: &mut Frame) {
+        let area = centered_rect(60, 50, frame.area());
+        let items: Vec<ListItem> = self.items.iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(self.title.as_str()))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        frame.render_widget(Clear, area); // popup must not show the table through
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
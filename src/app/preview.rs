@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use ratatui::{
+    layout::Rect,
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+static HEX_ROW: usize = 16;
+
+// head of the file under the cursor, kept until the cursor moves to another file
+pub struct Preview {
+    pub path: PathBuf,
+    pub bytes: io::Result<Vec<u8>>,
+    pub truncated: bool, // the file goes on past what was read
+}
+
+impl Preview {
+    pub fn load(path: PathBuf, limit: u64) -> Self {
+        let (bytes, truncated) = match read_head(&path, limit) {
+            Ok((bytes, truncated)) => (Ok(bytes), truncated),
+            Err(error) => (Err(error), false),
+        };
+        Preview { path, bytes, truncated }
+    }
+}
+
+// only the first `limit` bytes, previews must stay cheap on big files
+fn read_head(path: &PathBuf, limit: u64) -> io::Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    // one byte extra tells whether anything was left out
+    File::open(path)?.take(limit + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() as u64 > limit;
+    bytes.truncate(limit as usize);
+    Ok((bytes, truncated))
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0)
+}
+
+// both renderers take the wanted scroll offset and return it clamped to the content
+pub fn render_text_preview(frame: &mut Frame, area: Rect, bytes: &[u8], truncated: bool, scroll: u16) -> u16 {
+    // control characters would mess up the terminal
+    let text: String = String::from_utf8_lossy(bytes)
+        .chars()
+        .map(|c| match c {
+            '\t' => ' ',
+            c if c.is_control() && c != '\n' => '.',
+            c => c,
+        })
+        .collect();
+
+    let title = if truncated { format!("Preview (first {} B)", bytes.len()) } else { "Preview".to_string() };
+    let scroll = clamp_scroll(scroll, text.lines().count());
+    let widget = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((scroll, 0));
+    frame.render_widget(widget, area);
+    scroll
+}
+
+// `hexdump -C` layout: offset, two groups of 8 hex bytes, ascii gutter
+pub fn render_hex_preview(frame: &mut Frame, area: Rect, bytes: &[u8], truncated: bool, scroll: u16) -> u16 {
+    let mut text = String::new();
+
+    for (row, chunk) in bytes.chunks(HEX_ROW).enumerate() {
+        text.push_str(&format!("{:08x}  ", row * HEX_ROW));
+        for i in 0..HEX_ROW {
+            match chunk.get(i) {
+                Some(byte) => text.push_str(&format!("{:02x} ", byte)),
+                None => text.push_str("   "),
+            }
+            if i == HEX_ROW / 2 - 1 {
+                text.push(' ');
+            }
+        }
+
+        text.push('|');
+        for byte in chunk {
+            text.push(if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' });
+        }
+        text.push_str("|\n");
+    }
+
+    let title = if truncated { format!("Preview [Hex] (first {} B)", bytes.len()) } else { "Preview [Hex]".to_string() };
+    let scroll = clamp_scroll(scroll, bytes.len().div_ceil(HEX_ROW));
+    let widget = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((scroll, 0));
+    frame.render_widget(widget, area);
+    scroll
+}
+
+// the last line may come up to the top, not further
+fn clamp_scroll(scroll: u16, lines: usize) -> u16 {
+    scroll.min(lines.saturating_sub(1).min(u16::MAX as usize) as u16)
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub dirs_by_name: bool, // sort mode applies to files only, dirs stay alphabetical
+    pub esc_action: EscAction, // what Esc does outside of input mode
+    pub wrap_names: bool, // long names take a second row instead of being cut
+    pub picker_command: String, // external fuzzy picker, gets the listing on stdin
+    pub enter_new_dirs: bool, // `m` behaves like `M` and enters the new dir
+    pub protected_paths: Vec<String>, // never deleted or overwritten, "/**" suffix covers the subtree
+    pub case_sensitive_search: bool, // initial search mode, Alt-c toggles
+    pub fuzzy_search: bool, // subsequence matching ranked by score, Alt-f toggles
+    pub search_filters: bool, // search hides what does not match, otherwise marks it in place; Alt-h toggles
+    pub search_depth: usize, // how many levels Ctrl-/ descends
+    pub preview_bytes: u64, // how much of a file the preview pane reads
+    pub open_with: Vec<OpenWithApp>, // entries of the "open with" menu
+    pub status_icons: StatusIcons, // glyph in front of the status message
+    pub backup_on_overwrite: bool, // paste/rename onto an existing name keeps it as "name~"
+    pub git_status: bool, // git state column, runs `git status` on every listing
+    pub use_trash: bool, // `d` and `D` move to the XDG trash instead of deleting, undoable
+    pub time_format: TimeFormat, // modified column, `T` shows it
+    pub watch_dir: bool, // refresh when other programs change the listed dir (inotify)
+    pub mouse: bool, // off by default, some terminals and tmux setups fight over mouse mode
+    pub restore_dir: bool, // start where the last run quit, like `--restore`
+    pub confirm_quit: bool, // `q` asks first while copies run or a cut is not pasted
+    pub icons: bool, // Nerd Font glyph in front of each name, needs a patched font
+    pub wrap_navigation: bool, // `j` on the last row goes to the first and `k` the other way, otherwise they stop
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            dirs_by_name: false,
+            esc_action: EscAction::ClearSearch,
+            wrap_names: false,
+            picker_command: "fzf".to_string(),
+            enter_new_dirs: false,
+            protected_paths: vec!["/".to_string(), "~".to_string()],
+            case_sensitive_search: false,
+            fuzzy_search: true,
+            search_filters: true,
+            search_depth: 8,
+            preview_bytes: 4096,
+            open_with: Vec::new(),
+            status_icons: StatusIcons::default(),
+            backup_on_overwrite: false,
+            git_status: false,
+            use_trash: false,
+            time_format: TimeFormat::Relative,
+            watch_dir: true,
+            mouse: false,
+            restore_dir: false,
+            confirm_quit: true,
+            icons: false,
+            wrap_navigation: true,
+        }
+    }
+}
+
+// empty strings turn a glyph off, e.g. for terminals without these characters
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct StatusIcons {
+    pub ready: String,
+    pub error: String,
+    pub input: String,
+    pub others: String,
+}
+
+impl Default for StatusIcons {
+    fn default() -> Self {
+        StatusIcons {
+            ready: "✓".to_string(),
+            error: "✗".to_string(),
+            input: "⋯".to_string(),
+            others: "•".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct OpenWithApp {
+    pub name: String,
+    pub command: String, // gets the file as last argument
+    #[serde(default)]
+    pub gui: bool,       // runs detached instead of taking over the terminal
+}
+
+#[derive(Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EscAction {
+    ClearSearch,
+    ClearMarks,
+    ClearBoth,
+    None,
+}
+
+#[derive(Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    Relative, // "2h ago"
+    Absolute, // "2024-05-01 13:37"
+}
+
+impl Config {
+    // missing file means defaults, a broken one is reported before the TUI takes the screen
+    pub fn load() -> Config {
+        let path = match config_dir() {
+            Some(dir) => dir.join("config.toml"),
+            None => return Config::default(),
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|error| {
+                eprintln!("npns: ignoring {}: {}", path.display(), error);
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME/npns, or ~/.config/npns
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("npns"))
+}
+
+// "~" and "~/x" relative to $HOME, anything else as is
+pub fn expand_home(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') =>
+            home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
@@ -1,3 +1,12 @@
+pub mod archive;
+pub mod dir_loader;
 pub mod file_info;
 pub mod file_ops;
-pub mod file_system_info;
\ No newline at end of file
+pub mod file_system_info;
+pub mod git_status;
+pub mod ops_queue;
+pub mod owner;
+#[cfg(test)]
+pub mod test_dir;
+pub mod trash;
+pub mod watcher;
\ No newline at end of file
@@ -0,0 +1,229 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+// by extension, then by the first bytes for archives named some other way
+pub fn detect(path: &Path) -> Option<Format> {
+    if let Some(format) = path.file_name().and_then(|name| by_name(&name.to_string_lossy())) {
+        return Some(format);
+    }
+
+    let mut head = [0; 262];
+    let read = File::open(path).and_then(|mut file| file.read(&mut head)).ok()?;
+    match &head[..read] {
+        [b'P', b'K', 3, 4, ..] => Some(Format::Zip),
+        [0x1f, 0x8b, ..] => Some(Format::TarGz), // a plain .gz of one file fails at extract
+        head if head.len() >= 262 && &head[257..262] == b"ustar" => Some(Format::Tar),
+        _ => None,
+    }
+}
+
+pub fn by_name(name: &str) -> Option<Format> {
+    let name = name.to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+// "src.tar.gz" unpacks into "src", anything unknown into "name.d"
+pub fn dir_name(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let lower = name.to_lowercase();
+    let stem = [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext) && lower.len() > ext.len())
+        .map_or(format!("{}.d", name), |ext| name[..name.len() - ext.len()].to_string());
+    path.with_file_name(stem)
+}
+
+// into `target`, which must not exist yet; entries reaching outside of it are refused by both
+// crates. `progress` gets archive bytes read and stops the extract by returning false
+pub fn extract(archive: &Path, target: &Path, format: Format, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    std::fs::create_dir(target)?;
+    // the dir is ours from here on, nothing of it is worth keeping after a failure
+    unpack(archive, target, format, progress).inspect_err(|_| {
+        let _ = std::fs::remove_dir_all(target);
+    })
+}
+
+fn unpack(archive: &Path, target: &Path, format: Format, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    let file = File::open(archive)?;
+    match format {
+        Format::Zip => extract_zip(file, target, progress),
+        Format::Tar => extract_tar(tar::Archive::new(Counted { inner: file, progress }), target),
+        Format::TarGz => extract_tar(tar::Archive::new(GzDecoder::new(Counted { inner: file, progress })), target),
+    }
+}
+
+fn extract_tar<R: Read>(mut archive: tar::Archive<R>, target: &Path) -> std::io::Result<usize> {
+    let mut count = 0;
+    for entry in archive.entries()? {
+        entry?.unpack_in(target)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn extract_zip(file: File, target: &Path, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let path = match entry.enclosed_name() {
+            Some(path) => target.join(path),
+            None => continue, // "../x" or absolute
+        };
+        let mode = entry.unix_mode();
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+        } else if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            // a link keeps its target as the content; one leading out could carry later entries out too
+            let mut link_target = String::new();
+            entry.read_to_string(&mut link_target)?;
+            let stays_inside = Path::new(&link_target).components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir));
+            if stays_inside {
+                std::os::unix::fs::symlink(link_target, &path)?;
+            }
+            continue;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::io::copy(&mut entry, &mut File::create(&path)?)?;
+        }
+        if let Some(mode) = mode {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+        }
+        if !progress(entry.compressed_size()) {
+            return Err(cancelled());
+        }
+    }
+    Ok(archive.len())
+}
+
+// a new file at `target` holding each source under its own name, dirs with everything below;
+// links are stored as links. `progress` gets bytes of content added
+pub fn create(sources: &[PathBuf], target: &Path, format: Format, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    let file = File::options().write(true).create_new(true).open(target)?;
+    // the file is ours from here on, a half written archive is not kept
+    pack(file, sources, format, progress).inspect_err(|_| {
+        let _ = std::fs::remove_file(target);
+    })
+}
+
+fn pack(file: File, sources: &[PathBuf], format: Format, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    match format {
+        Format::Zip => {
+            let mut archive = zip::ZipWriter::new(file);
+            let mut count = 0;
+            for source in sources {
+                count += add_to_zip(&mut archive, source, &entry_name(source), progress)?;
+            }
+            archive.finish()?;
+            Ok(count)
+        }
+        Format::Tar => {
+            create_tar(Counted { inner: file, progress }, sources)?;
+            Ok(sources.len())
+        }
+        Format::TarGz => {
+            create_tar(Counted { inner: GzEncoder::new(file, Compression::default()), progress }, sources)?.inner.finish()?;
+            Ok(sources.len())
+        }
+    }
+}
+
+fn entry_name(source: &Path) -> PathBuf {
+    source.file_name().map(PathBuf::from).unwrap_or_default()
+}
+
+// the tar crate walks dirs itself, it only tells how many top level entries went in
+fn create_tar<W: Write>(writer: W, sources: &[PathBuf]) -> std::io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for source in sources {
+        if source.is_dir() && !source.is_symlink() {
+            builder.append_dir_all(entry_name(source), source)?;
+        } else {
+            builder.append_path_with_name(source, entry_name(source))?;
+        }
+    }
+    builder.into_inner()
+}
+
+fn add_to_zip(archive: &mut zip::ZipWriter<File>, source: &Path, name: &Path, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    let metadata = source.symlink_metadata()?;
+    let options = zip::write::SimpleFileOptions::default().unix_permissions(metadata.permissions().mode());
+    let zip_name = name.to_string_lossy();
+    if metadata.file_type().is_symlink() {
+        archive.add_symlink(zip_name, std::fs::read_link(source)?.to_string_lossy(), options)?;
+        return Ok(1);
+    }
+    if !metadata.is_dir() {
+        archive.start_file(zip_name, options)?;
+        let copied = std::io::copy(&mut File::open(source)?, archive)?;
+        if !progress(copied) {
+            return Err(cancelled());
+        }
+        return Ok(1);
+    }
+
+    archive.add_directory(zip_name, options)?;
+    let mut count = 1;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        count += add_to_zip(archive, &entry.path(), &name.join(entry.file_name()), progress)?;
+    }
+    Ok(count)
+}
+
+// what went through here is how far along it is: a tar is read front to back, and written that way
+struct Counted<'a, R> {
+    inner: R,
+    progress: &'a mut dyn FnMut(u64) -> bool,
+}
+
+impl<W: Write> Write for Counted<'_, W> {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buffer)?;
+        if !(self.progress)(written as u64) {
+            return Err(cancelled());
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buffer)?;
+        if !(self.progress)(read as u64) {
+            return Err(cancelled());
+        }
+        Ok(read)
+    }
+}
+
+// not Interrupted, readers retry on that
+fn cancelled() -> std::io::Error {
+    std::io::Error::other("Cancelled")
+}
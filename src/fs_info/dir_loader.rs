@@ -0,0 +1,125 @@
+use std::fs::{read_dir, DirEntry, Metadata};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::fs_info::file_info::FileInfo;
+
+static BATCH_SIZE: usize = 512;
+
+// reads a dir on its own thread and hands the entries over in batches,
+// a huge dir fills in while the UI keeps drawing; dropping it stops the read
+pub struct DirLoader {
+    batches: Receiver<Vec<FileInfo>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DirLoader {
+    // the dir is opened here, so a missing or unreadable one fails right away
+    pub fn start(dir: &Path) -> std::io::Result<Self> {
+        let entries = read_dir(dir)?;
+        let (sender, batches) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let worker_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let mut batch = Vec::with_capacity(BATCH_SIZE);
+            // one bad entry must not cost the whole listing
+            for entry in entries.flatten() {
+                if worker_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(file) = read_entry(entry) {
+                    batch.push(file);
+                }
+                if batch.len() == BATCH_SIZE && sender.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(batch);
+        });
+
+        Ok(DirLoader { batches, cancel })
+    }
+
+    // what arrived until `timeout` ran out, and whether that was everything
+    pub fn wait(&self, timeout: Duration) -> (Vec<FileInfo>, bool) {
+        let deadline = Instant::now() + timeout;
+        let mut files = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.batches.recv_timeout(left) {
+                Ok(mut batch) => files.append(&mut batch),
+                Err(RecvTimeoutError::Timeout) => return (files, false),
+                Err(RecvTimeoutError::Disconnected) => return (files, true),
+            }
+        }
+    }
+}
+
+impl Drop for DirLoader {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn read_entry(entry: DirEntry) -> Option<FileInfo> {
+    let path = entry.path();
+    let name = path.file_name()?.to_string_lossy().into_owned();
+
+    // like `ls -l`, links show as links, not as what they point to
+    let own = match entry.metadata() {
+        Ok(own) => own,
+        Err(_) => {
+            // still listed, with whatever the dir entry itself knows
+            let file_type = entry.file_type().ok()?;
+            return Some(FileInfo {
+                name,
+                is_dir: file_type.is_dir(),
+                path,
+                size: 0,
+                modified: None,
+                uid: 0,
+                gid: 0,
+                file_type,
+                mode: 0,
+                link_target: None,
+                broken_link: false,
+                unreadable: true,
+            });
+        }
+    };
+    Some(with_metadata(path, name, own))
+}
+
+// one entry by path, listed under `name`; None once it is gone
+pub fn read_path(path: &Path, name: String) -> Option<FileInfo> {
+    let own = path.symlink_metadata().ok()?;
+    Some(with_metadata(path.to_path_buf(), name, own))
+}
+
+fn with_metadata(path: PathBuf, name: String, own: Metadata) -> FileInfo {
+    // a dangling link still gets listed, with its own metadata
+    let target = path.metadata();
+    let broken_link = own.file_type().is_symlink() && target.is_err();
+    let metadata = target.unwrap_or_else(|_| own.clone());
+    let link_target = if own.file_type().is_symlink() { std::fs::read_link(&path).ok() } else { None };
+
+    FileInfo {
+        name,
+        path,
+        is_dir: metadata.is_dir(),
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        file_type: own.file_type(),
+        mode: own.mode(),
+        link_target,
+        broken_link,
+        unreadable: false,
+    }
+}
@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use std::ffi::CString;
+use std::fs::FileType;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct FileInfo {
@@ -6,4 +10,36 @@ pub struct FileInfo {
     pub path: PathBuf,
     pub size: u64,
     pub is_dir: bool,
+    pub modified: Option<SystemTime>,
+    pub uid: u32,
+    pub gid: u32,
+    pub file_type: FileType, // of the entry itself, a link is a link
+    pub mode: u32,           // permission bits, the link's own for links
+    pub link_target: Option<PathBuf>, // where a symlink points, as written in the link
+    pub broken_link: bool,            // symlink whose target does not exist
+    pub unreadable: bool,             // no metadata (permission denied, ...), sizes and ids are placeholders
+}
+
+// (available to us, total) bytes of the filesystem holding `path`
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_bavail as u64 * block, stat.f_blocks as u64 * block))
+}
+
+// broken down in the local timezone: (year, month, day, hour, minute, second)
+pub fn local_time(time: SystemTime) -> (i32, i32, i32, i32, i32, i32) {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as libc::time_t,
+        Err(before) => -(before.duration().as_secs() as libc::time_t),
+    };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::localtime_r(&seconds, &mut tm);
+    }
+    (tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
 }
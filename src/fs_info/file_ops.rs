@@ -1,13 +1,25 @@
 use std::path::PathBuf;
+use std::time::SystemTime;
 
+#[derive(Clone)]
 pub enum Operation {
     Copy,
     Cut,
     Rename,
     New,
+    NewDir,
     CD,
+    RemoveDir, // empty dir removed, file_source is the dir
+    Delete, // moved to the trash, file_source is where it was, file_target where it is now
+    Symlink, // file_target is a new link pointing at file_source
+    Extract, // file_source unpacked into the new dir file_target
+    Archive(Vec<PathBuf>), // these packed into the new file file_target
+    Chmod(u32), // previous mode of file_target
+    Touch(SystemTime, SystemTime), // previous (accessed, modified) of file_target
+    Batch(Vec<OpsUnit>), // several ops undone together, newest last
 }
 
+#[derive(Clone)]
 pub struct OpsUnit{
     pub operation: Operation,
     pub file_source: PathBuf,
@@ -1,13 +1,33 @@
 #![allow(dead_code)]
 
-use std::collections::VecDeque;
-use std::path::PathBuf;
-use std::fs::{read_dir};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::ffi::CString;
+use std::fs::{read_dir, Permissions};
+use std::io::{Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, SystemTime};
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::fs_info::archive;
+use crate::fs_info::dir_loader::{self, DirLoader};
 use crate::fs_info::file_info::FileInfo;
 use crate::fs_info::file_ops::{OpsUnit, Operation};
+use crate::fs_info::ops_queue::{Job, OpsQueue, Task};
+use crate::fs_info::git_status::{self, GitState};
+use crate::fs_info::trash::{self, Trash};
+use crate::fs_info::watcher::DirWatcher;
+use crate::config::{expand_home, Config};
 
 static MAX_HISTORY_SIZE: usize = 64;
+static MAX_CLIPBOARD_HISTORY: usize = 16;
+static COPY_CHUNK: usize = 256 * 1024;
+static LOAD_WAIT: Duration = Duration::from_millis(150);
+
+// (paths and is_copy, older entries newest first), moved between panes as a whole
+pub type Clipboard = (Option<(Vec<PathBuf>, bool)>, VecDeque<(PathBuf, bool)>);
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum StatusFlag{
@@ -17,26 +37,125 @@ pub enum StatusFlag{
     Others
 }
 
+// what paste does when the name is already taken in the target dir
+#[derive(PartialEq, Clone, Copy)]
+pub enum Conflict {
+    Refuse,
+    Overwrite, // the old entry is kept as "name~" so undo can bring it back
+    Rename,    // "name (1).ext", the first free one
+    Skip,
+}
+
+#[derive(PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode{
+    #[default]
+    Name,
+    Size,          // biggest first
+    Modified,      // newest first
+    ExtModified,   // grouped by extension, newest first within each
+    NameLength,    // shortest first
+    Type,          // by extension, then name
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Size,
+            SortMode::Size => SortMode::Modified,
+            SortMode::Modified => SortMode::ExtModified,
+            SortMode::ExtModified => SortMode::NameLength,
+            SortMode::NameLength => SortMode::Type,
+            SortMode::Type => SortMode::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "Name",
+            SortMode::Size => "Size",
+            SortMode::Modified => "Modified",
+            SortMode::ExtModified => "Extension, Modified",
+            SortMode::NameLength => "Name Length",
+            SortMode::Type => "Type",
+        }
+    }
+
+    // as typed after ":sort"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(SortMode::Name),
+            "size" => Some(SortMode::Size),
+            "modified" | "time" => Some(SortMode::Modified),
+            "ext" => Some(SortMode::ExtModified),
+            "length" => Some(SortMode::NameLength),
+            "type" => Some(SortMode::Type),
+            _ => None,
+        }
+    }
+
+    // smallest/oldest/a first; size and modified start with the biggest/newest instead
+    pub fn ascending(self) -> bool {
+        !matches!(self, SortMode::Size | SortMode::Modified)
+    }
+}
+
 pub struct FileSys{
     current_dir: PathBuf,
     files: Vec<FileInfo>,
-    pub selected_index: Option<usize>,
+    marked: HashSet<usize>, // indices into `files`, kept on the same entries across refreshes
     pub status_info: String,
     pub status_flag: StatusFlag,
-    clipboard: Option<(PathBuf, bool)>,
-    ops_history: VecDeque<OpsUnit>
+    clipboard: Option<(Vec<PathBuf>, bool)>, // (paths, is_copy)
+    clipboard_history: VecDeque<(PathBuf, bool)>, // newest first
+    ops_history: VecDeque<OpsUnit>,
+    redo_history: VecDeque<OpsUnit>, // undone ops, ready to be applied again
+    sort_mode: SortMode,
+    reversed: bool, // flips the mode's own direction, dirs still come first
+    dirs_by_name: bool,
+    protected: Vec<(PathBuf, bool)>, // (path, whole subtree)
+    backup_on_overwrite: bool,
+    queue: OpsQueue, // copies and deletes run here, off the UI thread
+    git_status: bool,
+    git_states: HashMap<String, GitState>, // by entry name, reloaded with the listing
+    git_ignored: HashSet<String>, // names git itself reports as ignored, what the hiding goes by
+    git_root: Option<(PathBuf, Option<PathBuf>)>, // (dir, its repo root), looked up again on cd
+    hide_ignored: bool, // git states are loaded for this too, even with the column off
+    trash: Option<Trash>, // deletes go here when trash mode is on
+    loader: Option<DirLoader>, // read of a big dir still coming in
+    loading_marks: HashSet<PathBuf>, // marks on entries the loader has not delivered yet
+    watcher: Option<DirWatcher>, // refreshes when other programs change the dir
+    found_in: Option<PathBuf>, // the listing holds search results from below this dir, named by their relative path
 }
 
 impl FileSys{
-    pub fn init(start_dir: PathBuf) -> Result<Self> {
+    pub fn init(start_dir: PathBuf, config: &Config) -> Result<Self> {
         let mut fs = FileSys{
             current_dir: start_dir,
             files: Vec::new(),
-            selected_index: None,
+            marked: HashSet::new(),
             status_info: "Initializing".to_string(),
             status_flag: StatusFlag::Others,
             clipboard: None,
-            ops_history: VecDeque::with_capacity(MAX_HISTORY_SIZE)
+            clipboard_history: VecDeque::with_capacity(MAX_CLIPBOARD_HISTORY),
+            ops_history: VecDeque::with_capacity(MAX_HISTORY_SIZE),
+            redo_history: VecDeque::with_capacity(MAX_HISTORY_SIZE),
+            sort_mode: SortMode::Name,
+            reversed: false,
+            dirs_by_name: config.dirs_by_name,
+            protected: config.protected_paths.iter().map(|entry| parse_protected(entry)).collect(),
+            backup_on_overwrite: config.backup_on_overwrite,
+            queue: OpsQueue::new(),
+            git_status: config.git_status,
+            git_states: HashMap::new(),
+            git_ignored: HashSet::new(),
+            git_root: None,
+            hide_ignored: false,
+            trash: if config.use_trash { Trash::locate() } else { None },
+            loader: None,
+            loading_marks: HashSet::new(),
+            watcher: if config.watch_dir { DirWatcher::new().ok() } else { None },
+            found_in: None,
         };
 
         fs.refresh()?;
@@ -44,30 +163,37 @@ impl FileSys{
     }
 
     pub fn refresh(&mut self) -> Result<()> {
-        self.files.clear();
-        for entry in read_dir(&self.current_dir)?{
-            let entry = entry?;
-            let path = entry.path();
-            let metadata = path.metadata()?;
+        // search results stay until the dir is left
+        if self.found_in.as_ref() == Some(&self.current_dir) {
+            self.refresh_found();
+            return Ok(());
+        }
+        self.found_in = None;
 
-            if let Some(file_name) = path.file_name() {
-                self.files.push(FileInfo{
-                    name: file_name.to_string_lossy().into_owned(),
-                    path,
-                    is_dir: metadata.is_dir(),
-                    size: metadata.len()
-                });
-            }
+        let mut marked = self.marked_paths();
+        marked.extend(self.loading_marks.drain());
+        // replacing the loader stops a read still going on elsewhere
+        let loader = match DirLoader::start(&self.current_dir) {
+            Ok(loader) => loader,
+            Err(error) => return self.move_up_from_lost(error),
+        };
+        // most dirs are done well within this and never show up half read
+        let (files, done) = loader.wait(LOAD_WAIT);
+        self.files = files;
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.follow(&self.current_dir);
         }
 
-        self.selected_index = None;
-        self.files.sort_by(|a, b| {
-            if a.is_dir != b.is_dir {
-                a.is_dir.cmp(&b.is_dir).reverse()
-            } else {
-                a.name.cmp(&b.name)
-            }
-        });
+        self.sort_files();
+        self.remap_marks(marked.clone());
+        if done {
+            self.loader = None;
+            self.finish_loading();
+        } else {
+            self.loader = Some(loader);
+            // entries still on the way keep their marks once they show up
+            self.loading_marks = marked;
+        }
 
         self.status_info = "Ready".to_string();
         self.status_flag = StatusFlag::Ready;
@@ -75,35 +201,295 @@ impl FileSys{
         Ok(())
     }
 
-    pub fn select_current(&mut self, current_index: usize){
-        self.selected_index = Some(current_index);
-        if current_index < self.files.len() {
-            let file = &self.files[current_index];
+    // the dir was removed (or locked) under us: list the nearest ancestor that can still be read
+    fn move_up_from_lost(&mut self, error: std::io::Error) -> Result<()> {
+        let lost = self.current_dir.clone();
+        let ancestor = match lost.ancestors().skip(1).find(|dir| read_dir(dir).is_ok()) {
+            Some(ancestor) => ancestor.to_path_buf(),
+            None => return Err(error.into()),
+        };
+        self.current_dir = ancestor;
+        self.refresh()?;
+
+        let reason = match error.kind() {
+            std::io::ErrorKind::NotFound => "Gone".to_string(),
+            _ => error.to_string(),
+        };
+        self.status_info = format!("{}: {}, Moved Up to {}", lost.display(), reason, self.current_dir.display());
+        self.status_flag = StatusFlag::Error;
+        Ok(())
+    }
+
+    // an empty listing for search results below the current dir to come into
+    pub fn show_found(&mut self) {
+        self.loader = None;
+        self.files.clear();
+        self.marked.clear();
+        self.loading_marks.clear();
+        self.found_in = Some(self.current_dir.clone());
+    }
+
+    pub fn add_found(&mut self, paths: &[PathBuf]) {
+        let marked = self.marked_paths();
+        for path in paths {
+            let name = path.strip_prefix(&self.current_dir).unwrap_or(path).to_string_lossy().into_owned();
+            self.files.extend(dir_loader::read_path(path, name));
+        }
+        self.sort_files();
+        self.remap_marks(marked);
+    }
+
+    // back to the plain listing on the next refresh
+    pub fn leave_found(&mut self) {
+        self.found_in = None;
+    }
+
+    pub fn is_showing_found(&self) -> bool {
+        self.found_in.is_some()
+    }
+
+    // results looked at again, what was moved or deleted meanwhile drops out
+    fn refresh_found(&mut self) {
+        let marked = self.marked_paths();
+        self.files = self.files.iter().filter_map(|file| dir_loader::read_path(&file.path, file.name.clone())).collect();
+        self.sort_files();
+        self.remap_marks(marked);
+    }
+
+    // picks up what a running load brought in since the last call, true when the listing changed
+    pub fn poll_loading(&mut self) -> bool {
+        let (mut files, done) = match &self.loader {
+            Some(loader) => loader.wait(Duration::ZERO),
+            None => return false,
+        };
+        if files.is_empty() && !done {
+            return false;
+        }
+
+        let mut marked = self.marked_paths();
+        marked.extend(self.loading_marks.iter().cloned());
+        self.files.append(&mut files);
+        self.sort_files();
+        self.remap_marks(marked);
+        if done {
+            self.loader = None;
+            self.loading_marks.clear();
+            self.finish_loading();
+        }
+        true
+    }
+
+    // the dir changed behind our back and things have calmed down
+    pub fn changed_on_disk(&mut self) -> bool {
+        self.watcher.as_mut().is_some_and(|watcher| watcher.settled())
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    fn finish_loading(&mut self) {
+        // every operation ends in a refresh, so this also catches changes we made
+        (self.git_states, self.git_ignored) = match self.repo_root() {
+            Some(root) => git_status::load(&self.current_dir, &root),
+            _ => (HashMap::new(), HashSet::new()),
+        };
+    }
+
+    // outside a repo that is one `git` run per dir, not one per refresh
+    fn repo_root(&mut self) -> Option<PathBuf> {
+        if !self.git_status && !self.hide_ignored {
+            return None;
+        }
+        match &self.git_root {
+            Some((dir, root)) if *dir == self.current_dir => root.clone(),
+            _ => {
+                let root = git_status::repo_root(&self.current_dir);
+                self.git_root = Some((self.current_dir.clone(), root.clone()));
+                root
+            }
+        }
+    }
+
+    pub fn toggle_git_status(&mut self) -> Result<()> {
+        self.git_status = !self.git_status;
+        self.git_root = None; // turning it off and on again picks up a fresh `git init`
+        self.refresh()
+    }
+
+    pub fn toggle_hide_ignored(&mut self) -> Result<()> {
+        self.hide_ignored = !self.hide_ignored;
+        self.refresh()?;
+        self.status_info = if self.hide_ignored { "Git Ignored Hidden" } else { "Git Ignored Shown" }.to_string();
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.set_sort(self.sort_mode.next());
+    }
+
+    // as the last session left it, quietly
+    pub fn restore_sort(&mut self, mode: SortMode, reversed: bool) {
+        self.sort_mode = mode;
+        self.reversed = reversed;
+        self.resort();
+    }
+
+    pub fn set_sort(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+        self.resort();
+        self.status_info = format!("Sort: {}", self.sort_mode.label());
+        self.status_flag = StatusFlag::Others;
+    }
+
+    // dirs first, each group sorted on its own so dirs can keep name order
+    fn sort_files(&mut self) {
+        let mode = self.sort_mode;
+        let (mut dirs, mut files): (Vec<FileInfo>, Vec<FileInfo>) =
+            self.files.drain(..).partition(|file| file.is_dir);
+
+        // a dir's own size says nothing about its contents
+        if self.dirs_by_name || mode == SortMode::Size {
+            dirs.sort_by(|a, b| compare_files(SortMode::Name, a, b));
+        } else {
+            dirs.sort_by(|a, b| compare_files(mode, a, b));
+        }
+        files.sort_by(|a, b| compare_files(mode, a, b));
+        if self.reversed {
+            if !self.dirs_by_name {
+                dirs.reverse();
+            }
+            files.reverse();
+        }
+
+        dirs.append(&mut files);
+        self.files = dirs;
+    }
+
+    // same mode, other direction; only reorders what is already loaded
+    pub fn toggle_sort_order(&mut self) {
+        self.reversed = !self.reversed;
+        self.resort();
+        self.status_info = format!("Sort: {}{}", self.sort_mode.label(), if self.reversed { ", Reversed" } else { "" });
+        self.status_flag = StatusFlag::Others;
+    }
+
+    fn resort(&mut self) {
+        let marked = self.marked_paths();
+        self.sort_files();
+        self.remap_marks(marked); // indices moved
+    }
+
+    pub fn mark(&mut self, index: usize) {
+        if let Some(file) = self.files.get(index) {
+            self.marked.insert(index);
             self.status_info = format!("Selected: {}", file.name);
             self.status_flag = StatusFlag::Others;
         }
     }
 
-    pub fn copy_selected(&mut self, is_copy: bool) -> Result<()>{
-        if let Some(selected_index) = self.selected_index {
-            let file = self.files.get(selected_index).cloned().unwrap();
-            if !file.is_dir {
-                self.clipboard = Some((file.path.clone(), is_copy));
-                self.status_info = format!("{}: {}", if is_copy { "Copied" } else { "Cut" }, file.name);
-                self.status_flag = StatusFlag::Others;
-            } else {
-                self.status_info = "Operation Not Supported".to_string();
-                self.status_flag = StatusFlag::Error;
+    pub fn toggle_mark(&mut self, index: usize) {
+        if !self.marked.remove(&index) {
+            self.mark(index);
+        }
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn unmark(&mut self, indices: &[usize]) {
+        for index in indices {
+            self.marked.remove(index);
+        }
+    }
+
+    // marks `indices`, or flips each of them with `invert`; other marks stay as they are
+    pub fn mark_all(&mut self, indices: &[usize], invert: bool) {
+        for &index in indices.iter().filter(|&&index| index < self.files.len()) {
+            let was_unmarked = self.marked.insert(index);
+            if invert && !was_unmarked {
+                self.marked.remove(&index);
             }
-        } else {
+        }
+        self.status_info = format!("{} Marked", self.marked.len());
+        self.status_flag = StatusFlag::Others;
+    }
+
+    // marked entries in listing order
+    pub fn marked_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.marked.iter().copied().collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    // the one marked entry, None when nothing or several are marked
+    pub fn single_marked(&self) -> Option<usize> {
+        match self.marked.len() {
+            1 => self.marked.iter().next().copied(),
+            _ => None,
+        }
+    }
+
+    fn marked_paths(&self) -> HashSet<PathBuf> {
+        self.marked.iter()
+            .filter_map(|index| self.files.get(*index))
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    // after the listing was rebuilt or reordered, marks follow their paths; gone entries drop out
+    fn remap_marks(&mut self, marked: HashSet<PathBuf>) {
+        self.marked = self.files.iter()
+            .enumerate()
+            .filter(|(_, file)| marked.contains(&file.path))
+            .map(|(index, _)| index)
+            .collect();
+    }
+
+    pub fn copy_entries(&mut self, indices: &[usize], is_copy: bool) -> Result<()>{
+        let files: Vec<FileInfo> = indices.iter().filter_map(|index| self.files.get(*index).cloned()).collect();
+        if files.is_empty() {
             self.status_info = "No File Selected".to_string();
             self.status_flag = StatusFlag::Error;
+            return Ok(());
         }
+        for file in &files {
+            self.remember_clipboard(file.path.clone(), is_copy);
+        }
+        self.clipboard = Some((files.iter().map(|file| file.path.clone()).collect(), is_copy));
+        self.status_info = format!("{}: {}", if is_copy { "Copied" } else { "Cut" }, describe(&files));
+        self.status_flag = StatusFlag::Others;
         Ok(())
     }
 
     pub fn paste(&mut self) -> Result<()>{
-        let (source, is_copy) = match &self.clipboard {
+        self.paste_with(self.default_conflict(), None, false)
+    }
+
+    pub fn default_conflict(&self) -> Conflict {
+        if self.backup_on_overwrite { Conflict::Overwrite } else { Conflict::Refuse }
+    }
+
+    // clipboard entries whose name is already taken in the target dir, asked about before pasting;
+    // with backup_on_overwrite the answer is always overwrite, nothing to ask
+    pub fn paste_conflicts(&self, dir: Option<&Path>) -> usize {
+        let sources = match &self.clipboard {
+            Some((sources, _)) if !self.backup_on_overwrite => sources,
+            _ => return 0,
+        };
+        let target_dir = dir.map_or_else(|| self.target_dir(), Path::to_path_buf);
+        sources.iter()
+            .filter_map(|source| source.file_name())
+            .filter(|name| self.is_taken(&target_dir.join(name)))
+            .count()
+    }
+
+    // into `dir`, or where `target_dir` says when there is none; `link` leaves the sources
+    // where they are and puts symlinks to them there instead
+    pub fn paste_with(&mut self, policy: Conflict, dir: Option<&Path>, link: bool) -> Result<()>{
+        let (sources, is_copy) = match &self.clipboard {
             Some((clipboard, is_copy)) => (clipboard.clone(), *is_copy),
             None => {
                 self.status_info = "Clipboard is empty".to_string();
@@ -112,187 +498,885 @@ impl FileSys{
             },
         };
 
-        if !source.exists() {
+        if sources.iter().any(|source| !source.exists()) {
             self.status_info = "Source file does not exist".to_string();
             self.status_flag = StatusFlag::Error;
             self.clipboard = None;
+            self.clipboard_history.retain(|(path, _)| path.exists());
             return Ok(());
         };
 
-        let target_dir = match self.selected_index {
-            Some(index) => {
-                if self.files.get(index).unwrap().is_dir {
-                    self.files.get(index).unwrap().path.clone()
-                }  else {
-                    self.current_dir.clone()
+        let target_dir = dir.map_or_else(|| self.target_dir(), Path::to_path_buf);
+        let mut moved = Vec::new(); // instant, undone together
+        let mut pasted = Vec::new();
+        let mut failed = Vec::new();
+        let mut skipped = 0;
+        for source in sources {
+            let name = source.file_name().ok_or_else(||anyhow!("Invalid file name"))?.to_string_lossy().into_owned();
+            if policy == Conflict::Skip && self.is_taken(&target_dir.join(&name)) {
+                skipped += 1;
+                continue;
+            }
+            let pasted_one = if link {
+                self.link_one(source, &target_dir, policy).map(|(op, backup_name)| (Some(op), backup_name))
+            } else {
+                self.paste_one(source, is_copy, &target_dir, policy)
+            };
+            match pasted_one {
+                Ok((op, backup_name)) => {
+                    moved.extend(op);
+                    pasted.push(format!("{}{}", name, backup_name));
                 }
+                Err(reason) => failed.push(format!("{} ({})", name, reason)),
+            }
+        }
+        if !moved.is_empty() {
+            self.push_history(batch_of(moved));
+        }
+
+        self.refresh()?;
+        let verb = if link { "Linked" } else if is_copy { "Queued" } else { "Pasted" };
+        self.status_info = match (pasted.len(), failed.len()) {
+            (_, 0) if pasted.len() == 1 => format!("{}: {}", verb, pasted[0]),
+            (count, 0) => format!("{}: {}", verb, count),
+            (0, 1) => failed[0].clone(),
+            (count, _) => format!("{}: {}, Failed: {}", verb, count, failed.join(", ")),
+        };
+        if skipped > 0 {
+            self.status_info = format!("{}, Skipped: {}", self.status_info, skipped);
+        }
+        self.status_flag = if failed.is_empty() { StatusFlag::Others } else { StatusFlag::Error };
+        Ok(())
+    }
+
+    // a move is done right away and returned for undo, a copy goes to the queue
+    fn paste_one(&mut self, source: PathBuf, is_copy: bool, target_dir: &Path, policy: Conflict) -> Result<(Option<OpsUnit>, String), String> {
+        let file_name = source.file_name().ok_or("Invalid file name")?.to_owned();
+        let mut target_path = target_dir.join(&file_name);
+
+        if target_path.starts_with(&source) && target_path != source {
+            return Err("Into Itself".to_string());
+        }
+
+        let mut backup = None;
+        if self.is_taken(&target_path) && policy == Conflict::Rename {
+            target_path = free_name(&target_path, |path| self.is_taken(path));
+        } else if self.is_taken(&target_path) {
+            if policy != Conflict::Overwrite || target_path == source {
+                return Err("File already exists".to_string());
+            }
+            if target_path.symlink_metadata().is_err() {
+                return Err("Name Taken by a Queued Job".to_string());
+            }
+            if self.is_protected(&target_path) {
+                return Err(format!("Protected: {}", target_path.display()));
+            }
+            backup = Some(backup_existing(&target_path).map_err(|error| error.to_string())?);
+        }
+
+        let op = OpsUnit {
+            operation: if is_copy { Operation::Copy } else { Operation::Cut },
+            file_source: source.clone(),
+            file_target: target_path.clone()
+        };
+        let backup_name = match target_path.file_name() {
+            Some(name) if name != file_name => format!(" (as {})", name.to_string_lossy()),
+            _ => backup_name(&backup),
+        };
+
+        // a copy may take a while, a move within the filesystem is instant
+        if is_copy {
+            let label = format!("Copy {}", target_path.file_name().unwrap_or(&file_name).to_string_lossy());
+            self.queue.push(label, Task::Copy(source, target_path), Some(op), backup);
+            return Ok((None, backup_name));
+        }
+
+        if let Err(error) = std::fs::rename(&source, &target_path) {
+            restore_backup(&backup);
+            return Err(error.to_string());
+        }
+        Ok((Some(with_backup(op, backup)), backup_name))
+    }
+
+    // like a move, instant and returned for undo
+    fn link_one(&self, source: PathBuf, target_dir: &Path, policy: Conflict) -> Result<(OpsUnit, String), String> {
+        let file_name = source.file_name().ok_or("Invalid file name")?.to_owned();
+        let mut target_path = target_dir.join(&file_name);
+
+        let mut backup = None;
+        if self.is_taken(&target_path) && policy == Conflict::Rename {
+            target_path = free_name(&target_path, |path| self.is_taken(path));
+        } else if self.is_taken(&target_path) {
+            if policy != Conflict::Overwrite || target_path == source {
+                return Err("File already exists".to_string());
+            }
+            if target_path.symlink_metadata().is_err() {
+                return Err("Name Taken by a Queued Job".to_string());
             }
-            None => self.current_dir.clone()
+            if self.is_protected(&target_path) {
+                return Err(format!("Protected: {}", target_path.display()));
+            }
+            backup = Some(backup_existing(&target_path).map_err(|error| error.to_string())?);
+        }
+
+        if let Err(error) = std::os::unix::fs::symlink(&source, &target_path) {
+            restore_backup(&backup);
+            return Err(error.to_string());
+        }
+        let backup_name = match target_path.file_name() {
+            Some(name) if name != file_name => format!(" (as {})", name.to_string_lossy()),
+            _ => backup_name(&backup),
         };
+        let op = OpsUnit {
+            operation: Operation::Symlink,
+            file_source: source,
+            file_target: target_path,
+        };
+        Ok((with_backup(op, backup), backup_name))
+    }
+
+    // a cut only moves on paste, quitting before that forgets it
+    pub fn has_cut(&self) -> bool {
+        matches!(self.clipboard, Some((_, false)))
+    }
+
+    // the clipboard follows the user from pane to pane
+    pub fn take_clipboard(&mut self) -> Clipboard {
+        (self.clipboard.take(), std::mem::take(&mut self.clipboard_history))
+    }
+
+    pub fn set_clipboard(&mut self, (clipboard, history): Clipboard) {
+        self.clipboard = clipboard;
+        self.clipboard_history = history;
+    }
+
+    // puts an older entry back on the clipboard, the paste itself is up to the caller
+    pub fn use_from_history(&mut self, index: usize) -> bool {
+        match self.clipboard_history.get(index).cloned() {
+            Some((path, is_copy)) => {
+                self.clipboard = Some((vec![path], is_copy));
+                true
+            }
+            None => {
+                self.status_info = "Clipboard is empty".to_string();
+                self.status_flag = StatusFlag::Error;
+                false
+            }
+        }
+    }
 
-        let file_name = source.file_name().ok_or_else(||anyhow!("Invalid file name"))?;
-        let target_path = target_dir.join(file_name);
+    // same path copied again moves to the front instead of showing up twice
+    fn remember_clipboard(&mut self, path: PathBuf, is_copy: bool) {
+        self.clipboard_history.retain(|(old, _)| *old != path);
+        if self.clipboard_history.len() == MAX_CLIPBOARD_HISTORY {
+            self.clipboard_history.pop_back();
+        }
+        self.clipboard_history.push_front((path, is_copy));
+    }
 
-        if target_path.exists() {
-            self.status_info = "File already exists".to_string();
+    // `indices` only, marks on other entries stay
+    pub fn delete_entries(&mut self, indices: &[usize]) -> Result<()>{
+        let sources: Vec<PathBuf> = indices
+            .iter()
+            .filter_map(|index| self.files.get(*index))
+            .map(|file| file.path.clone())
+            .collect();
+        if sources.is_empty() {
+            self.status_info = "No Selected".to_string();
             self.status_flag = StatusFlag::Error;
             return Ok(());
         }
+        if self.trash.is_some() {
+            self.unmark(indices);
+            return self.delete_to_trash(sources);
+        }
 
-        let op = if is_copy {
-            std::fs::copy(&source, &target_path)?;
-            OpsUnit {
-                operation: Operation::Copy,
-                file_source: source.clone(),
-                file_target: target_path.clone()
+        let mut queued = Vec::new();
+        let mut protected = Vec::new();
+        for source in sources {
+            let name = source.file_name().unwrap().to_string_lossy().into_owned();
+            if self.is_protected(&source) {
+                protected.push(name);
+            } else {
+                queued.push((name, source));
             }
-        } else {
-            std::fs::rename(&source, &target_path)?;
-            OpsUnit {
-                operation: Operation::Cut,
-                file_source: source.clone(),
-                file_target: target_path.clone()
+        }
+        self.unmark(indices);
+        // one job for the lot, so a single line says how it went
+        match queued.as_slice() {
+            [] => {}
+            [(name, source)] => self.queue.push(format!("Delete {}", name), Task::Delete(source.clone()), None, None),
+            _ => {
+                let paths = queued.iter().map(|(_, source)| source.clone()).collect();
+                self.queue.push(format!("Delete {} Entries", queued.len()), Task::DeleteAll(paths), None, None);
             }
+        }
+        let queued: Vec<String> = queued.into_iter().map(|(name, _)| name).collect();
+
+        if protected.is_empty() {
+            self.status_info = match queued.as_slice() {
+                [name] => format!("Queued: Delete {}", name),
+                _ => format!("Queued: Delete {}", queued.len()),
+            };
+            self.status_flag = StatusFlag::Others;
+        } else {
+            self.status_info = format!("Queued: Delete {}, Protected: {}", queued.len(), protected.join(", "));
+            self.status_flag = StatusFlag::Error;
+        }
+        Ok(())
+    }
+
+    // trashing is a rename, so it runs right away and is undone as one step
+    pub fn delete_to_trash(&mut self, sources: Vec<PathBuf>) -> Result<()> {
+        let trash = match &self.trash {
+            Some(trash) => trash,
+            None => return Err(anyhow!("Trash not available")),
         };
 
-        Self::push_history(&mut self.ops_history, op);
+        let mut ops = Vec::new();
+        let mut trashed = Vec::new();
+        let mut kept = Vec::new(); // trash is on another filesystem
+        let mut failed = Vec::new();
+        for source in sources {
+            let name = source.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if self.is_protected(&source) {
+                failed.push(format!("{} (Protected)", name));
+                continue;
+            }
+            match trash.put(&source) {
+                Ok(target) => {
+                    ops.push(OpsUnit {
+                        operation: Operation::Delete,
+                        file_source: source,
+                        file_target: target,
+                    });
+                    trashed.push(name);
+                }
+                Err(error) if trash::crosses_devices(&error) => kept.push(name),
+                Err(error) => failed.push(format!("{} ({})", name, error)),
+            }
+        }
+        if !ops.is_empty() {
+            self.push_history(batch_of(ops));
+        }
+
         self.refresh()?;
-        self.status_info = format!("Pasted: {}", file_name.to_string_lossy());
-        self.status_flag = StatusFlag::Others;
+        let mut status = match trashed.as_slice() {
+            [name] => format!("Trashed: {}", name),
+            _ => format!("Trashed: {}", trashed.len()),
+        };
+        if !kept.is_empty() {
+            status = format!("{}, Kept (trash on another filesystem): {}", status, kept.join(", "));
+        }
+        if !failed.is_empty() {
+            status = format!("{}, Failed: {}", status, failed.join(", "));
+        }
+        self.status_info = status;
+        self.status_flag = if kept.is_empty() && failed.is_empty() { StatusFlag::Others } else { StatusFlag::Error };
         Ok(())
     }
 
-    pub fn delete_selected(&mut self) -> Result<()>{
-        let source = match self.selected_index {
-            Some(index) => self.files.get(index).cloned().unwrap().path,
+    // picks up what the queue finished since the last call, true when the listing was refreshed
+    pub fn poll_jobs(&mut self) -> Result<bool> {
+        let finished = self.queue.finished();
+        if finished.is_empty() {
+            return Ok(false);
+        }
+
+        self.refresh()?;
+        for (job, result) in finished {
+            match result {
+                Ok(count) => {
+                    if let Some(op) = job.undo {
+                        let op = with_backup(op, job.backup);
+                        if job.redone {
+                            Self::push_bounded(&mut self.ops_history, op);
+                        } else {
+                            self.push_history(op);
+                        }
+                    }
+                    self.status_info = match count {
+                        1 => format!("Done: {}", job.label),
+                        _ => format!("Done: {} ({} entries)", job.label, count),
+                    };
+                    self.status_flag = StatusFlag::Others;
+                }
+                Err(error) => {
+                    restore_backup(&job.backup);
+                    self.return_to_redo(&job);
+                    self.status_info = format!("Failed: {}: {}", job.label, error);
+                    self.status_flag = StatusFlag::Error;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    // a redo that did not happen can be tried again
+    fn return_to_redo(&mut self, job: &Job) {
+        if let (true, Some(op)) = (job.redone, &job.undo) {
+            Self::push_bounded(&mut self.redo_history, op.clone());
+        }
+    }
+
+    pub fn cancel_job(&mut self, id: u64) {
+        match self.queue.cancel(id) {
+            Some(job) => {
+                restore_backup(&job.backup);
+                self.return_to_redo(&job);
+                self.status_info = format!("Cancelled: {}", job.label);
+                self.status_flag = StatusFlag::Others;
+            }
             None => {
-                self.status_info = "No Selected".to_string();
+                self.status_info = "Already Started".to_string();
                 self.status_flag = StatusFlag::Error;
-                return Ok(());
             }
-        };
+        }
+    }
 
-        if source.is_dir() {
-            std::fs::remove_dir_all(&source)?;
-        } else {
-            std::fs::remove_file(&source)?;
+    // removes every entry of the current dir, protected ones are left alone
+    pub fn empty_current_dir(&mut self) -> Result<()> {
+        if self.is_protected(&self.current_dir) {
+            self.status_info = format!("Protected: {}", self.current_dir.display());
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
         }
 
-        self.refresh()?;
-        self.status_info = format!("Deleted: {}", source.file_name().unwrap().to_string_lossy());
-        self.status_flag = StatusFlag::Others;
-        Ok(())
+        // like `d` on everything: to the trash when it is on, otherwise gone for good in the background
+        let indices: Vec<usize> = (0..self.files.len()).collect();
+        self.delete_entries(&indices)
     }
 
     pub fn new_file(&mut self, name: &str, is_dir: bool) -> Result<()> {
-        if validate_filename(&name).is_err() {
+        // "a/b/c" for dirs works like `mkdir -p`
+        if is_dir && name.contains('/') {
+            return self.new_dir_path(name);
+        }
+        if validate_filename(name).is_err() {
             self.status_info = "Invalid Name".to_string();
             self.status_flag = StatusFlag::Error;
             return Ok(());
         }
 
-        let target_dir = match self.selected_index {
-            Some(idx) => {
-                let selected = &self.files[idx];
-                if selected.is_dir {
-                    selected.path.join(name)
-                } else {
-                    self.current_dir.join(name)
-                }
-            }
-            None => self.current_dir.join(name),
-        };
-
-        let target_path = target_dir;
+        let target_path = self.target_dir().join(name);
 
-        if target_path.exists() {
+        if self.is_taken(&target_path) {
             self.status_info = format!("{} Exists", name);
             self.status_flag = StatusFlag::Error;
             return Ok(());
         }
 
-        let op = if is_dir {
+        if is_dir {
             std::fs::create_dir(&target_path)?;
-            self.status_info = format!("Dir Created: {}", name);
-            OpsUnit {
-                operation: Operation::New,
-                file_source: PathBuf::new(),
-                file_target: target_path,
-            }
         } else {
             std::fs::File::create(&target_path)?;
-            self.status_info = format!("File Created: {}", name);
-            OpsUnit {
-                operation: Operation::New,
-                file_source: PathBuf::new(),
-                file_target: target_path,
-            }
+        }
+        let op = OpsUnit {
+            operation: if is_dir { Operation::NewDir } else { Operation::New },
+            file_source: PathBuf::new(),
+            file_target: target_path,
         };
 
-        Self::push_history(&mut self.ops_history, op);
-        self.status_flag = StatusFlag::Others;
+        self.push_history(op);
+        // refresh resets the status, so report afterwards
         self.refresh()?;
+        self.status_info = format!("{} Created: {}", if is_dir { "Dir" } else { "File" }, name);
+        self.status_flag = StatusFlag::Others;
         Ok(())
     }
 
-    pub fn rename_selected(&mut self, new_name: &str) -> Result<()> {
-        if validate_filename(new_name).is_err() {
-            self.status_info = "Invalid Name".to_string();
+    // every missing level is created and recorded, undo removes only those, deepest first
+    fn new_dir_path(&mut self, path: &str) -> Result<()> {
+        if validate_relative_path(path).is_err() {
+            self.status_info = "Invalid Path".to_string();
             self.status_flag = StatusFlag::Error;
             return Ok(());
         }
 
-        let source = match self.selected_index {
-            Some(idx) => self.files.get(idx).cloned().unwrap().path,
-            None => {
-                self.status_info = "No Selection".to_string();
-                self.status_flag = StatusFlag::Error;
+        let mut created = Vec::new();
+        let mut dir = self.target_dir();
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            dir.push(component);
+            if dir.is_dir() {
+                continue;
+            }
+            if let Err(error) = std::fs::create_dir(&dir) {
+                for op in created.into_iter().rev() {
+                    let _ = self.revert(op);
+                }
+                self.refresh()?;
+                return Err(error.into());
+            }
+            created.push(OpsUnit {
+                operation: Operation::NewDir,
+                file_source: PathBuf::new(),
+                file_target: dir.clone(),
+            });
+        }
+
+        if created.is_empty() {
+            self.status_info = format!("{} Exists", path);
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        let count = created.len();
+        self.push_history(batch_of(created));
+        self.refresh()?;
+        self.status_info = format!("Dir Created: {} ({} new)", path, count);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    pub fn new_files(&mut self, names: &[&str]) -> Result<()> {
+        let target_dir = self.target_dir();
+        let mut created = Vec::new();
+        let mut failed = Vec::new();
+
+        for name in names {
+            let target_path = target_dir.join(name);
+            if validate_filename(name).is_err()
+                || target_path.exists()
+                || std::fs::File::create(&target_path).is_err()
+            {
+                failed.push(name.to_string());
+                continue;
+            }
+            created.push(OpsUnit {
+                operation: Operation::New,
+                file_source: PathBuf::new(),
+                file_target: target_path,
+            });
+        }
+
+        let count = created.len();
+        if count > 0 {
+            let op = OpsUnit {
+                operation: Operation::Batch(created),
+                file_source: PathBuf::new(),
+                file_target: target_dir,
+            };
+            self.push_history(op);
+        }
+
+        self.refresh()?;
+        if failed.is_empty() {
+            self.status_info = format!("Files Created: {}", count);
+            self.status_flag = StatusFlag::Others;
+        } else {
+            self.status_info = format!("Files Created: {}, Failed: {}", count, failed.join(", "));
+            self.status_flag = StatusFlag::Error;
+        }
+        Ok(())
+    }
+
+    // every entry gets `pattern` with {n} (counter), {name} (stem) and {ext} (".ext" or nothing)
+    // filled in; nothing is touched unless all new names are valid and free, one undo reverts all
+    pub fn batch_rename(&mut self, indices: &[usize], pattern: &str) -> Result<()> {
+        let sources: Vec<PathBuf> = indices.iter().filter_map(|index| self.files.get(*index)).map(|file| file.path.clone()).collect();
+        let width = sources.len().to_string().len();
+
+        let mut renames = Vec::new();
+        let mut taken = HashSet::new();
+        for (position, source) in sources.iter().enumerate() {
+            let name = expand_rename_pattern(pattern, source, position + 1, width);
+            if validate_filename(&name).is_err() {
+                self.status_info = format!("Invalid Name: {}", name);
+                self.status_flag = StatusFlag::Error;
                 return Ok(());
             }
-        };
-        let target = self.current_dir.join(new_name);
+            // next to the source, search results live below the current dir
+            let target = source.with_file_name(&name);
+            if target == *source {
+                continue;
+            }
+            if !taken.insert(target.clone()) || target.symlink_metadata().is_ok() {
+                self.status_info = format!("{} Exists", name);
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+            renames.push((source.clone(), target));
+        }
 
-        if target.exists() {
-            self.status_info = format!("{} Exists", new_name);
+        let mut done = Vec::new();
+        for (source, target) in renames {
+            if let Err(error) = std::fs::rename(&source, &target) {
+                // half a batch is worse than none
+                for op in done.into_iter().rev() {
+                    let _ = self.revert(op);
+                }
+                self.refresh()?;
+                return Err(error.into());
+            }
+            done.push(OpsUnit { operation: Operation::Rename, file_source: source, file_target: target });
+        }
+
+        let count = done.len();
+        if count > 0 {
+            self.push_history(batch_of(done));
+        }
+        self.refresh()?;
+        self.status_info = format!("Renamed {} of {}", count, sources.len());
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    pub fn rename_entry(&mut self, index: usize, new_name: &str) -> Result<()> {
+        if validate_filename(new_name).is_err() {
+            self.status_info = "Invalid Name".to_string();
             self.status_flag = StatusFlag::Error;
             return Ok(());
         }
 
+        let source = match self.files.get(index) {
+            Some(file) => file.path.clone(),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        let target = source.with_file_name(new_name);
+
+        let mut backup = None;
+        if target.exists() {
+            if !self.backup_on_overwrite || target == source {
+                self.status_info = format!("{} Exists", new_name);
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+            if self.is_protected(&target) {
+                self.status_info = format!("Protected: {}", target.display());
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+            backup = Some(backup_existing(&target)?);
+        }
+
         let op = OpsUnit {
             operation: Operation::Rename,
             file_source: source.clone(),
             file_target: target.clone(),
         };
-        std::fs::rename(&source, &target)?;
-        Self::push_history(&mut self.ops_history, op);
+        if let Err(error) = std::fs::rename(&source, &target) {
+            restore_backup(&backup);
+            return Err(error.into());
+        }
+        let backup_name = backup_name(&backup);
+        self.push_history(with_backup(op, backup));
+        self.refresh()?;
+        self.status_info = format!("Renamed to: {}{}", new_name, backup_name);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    // move everything in the dir at `index` up here, then drop the emptied dir
+    pub fn flatten_into_current(&mut self, dir: &Path, policy: Conflict) -> Result<()> {
+        let entries = match read_dir(dir) {
+            Ok(entries) if dir.is_dir() => entries,
+            Ok(_) => {
+                self.status_info = "Not Dir".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+            Err(error) => {
+                self.status_info = format!("{}: {}", dir.display(), error);
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        // whatever moved is recorded even when some of it failed
+        let mut ops = Vec::new();
+        let mut skipped = 0;
+        let mut failed = Vec::new();
+        for entry in entries {
+            let source = match entry {
+                Ok(entry) => entry.path(),
+                Err(error) => {
+                    failed.push(error.to_string());
+                    continue;
+                }
+            };
+            let name = match source.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            match self.flatten_one(source, dir, policy) {
+                Ok(Some(op)) => ops.push(op),
+                Ok(None) => skipped += 1,
+                Err(reason) => failed.push(format!("{} ({})", name, reason)),
+            }
+        }
+
+        let moved = ops.len();
+        if skipped == 0 && failed.is_empty() {
+            match std::fs::remove_dir(dir) {
+                Ok(()) => ops.push(OpsUnit {
+                    operation: Operation::RemoveDir,
+                    file_source: dir.to_path_buf(),
+                    file_target: PathBuf::new(),
+                }),
+                Err(error) => failed.push(format!("{} ({})", dir.display(), error)),
+            }
+        }
+        if !ops.is_empty() {
+            let op = OpsUnit {
+                operation: Operation::Batch(ops),
+                file_source: dir.to_path_buf(),
+                file_target: self.current_dir.clone(),
+            };
+            self.push_history(op);
+        }
+
+        self.refresh()?;
+        self.status_info = format!("Flattened: {} moved", moved);
+        self.status_flag = StatusFlag::Others;
+        if skipped > 0 {
+            self.status_info = format!("{}, {} skipped (name taken)", self.status_info, skipped);
+        }
+        if !failed.is_empty() {
+            self.status_info = format!("{}, Failed: {}", self.status_info, failed.join(", "));
+            self.status_flag = StatusFlag::Error;
+        }
+        if skipped > 0 || !failed.is_empty() {
+            self.status_info.push_str(", dir kept");
+        }
+        Ok(())
+    }
+
+    // entries of `dir` whose name is taken here, asked about before flattening like a paste
+    pub fn flatten_conflicts(&self, dir: &Path) -> usize {
+        if self.backup_on_overwrite {
+            return 0;
+        }
+        read_dir(dir)
+            .map(|entries| entries
+                .flatten()
+                .filter(|entry| self.is_taken(&self.current_dir.join(entry.file_name())))
+                .count())
+            .unwrap_or(0)
+    }
+
+    // one entry of `dir` up here, None when it was skipped over a taken name
+    fn flatten_one(&self, source: PathBuf, dir: &Path, policy: Conflict) -> Result<Option<OpsUnit>, String> {
+        let mut target = self.current_dir.join(source.file_name().unwrap_or_default());
+        let mut backup = None;
+        if self.is_taken(&target) {
+            match policy {
+                // the dir being emptied can not make way for its own entry
+                Conflict::Rename => target = free_name(&target, |path| self.is_taken(path)),
+                Conflict::Overwrite if target == dir => target = free_name(&target, |path| self.is_taken(path)),
+                Conflict::Overwrite if target.symlink_metadata().is_err() => return Err("Name Taken by a Queued Job".to_string()),
+                Conflict::Overwrite if self.is_protected(&target) => return Err(format!("Protected: {}", target.display())),
+                Conflict::Overwrite => backup = Some(backup_existing(&target).map_err(|error| error.to_string())?),
+                Conflict::Refuse | Conflict::Skip => return Ok(None),
+            }
+        }
+
+        if let Err(error) = std::fs::rename(&source, &target) {
+            restore_backup(&backup);
+            return Err(error.to_string());
+        }
+        let op = OpsUnit {
+            operation: Operation::Cut,
+            file_source: source,
+            file_target: target,
+        };
+        Ok(Some(with_backup(op, backup)))
+    }
+
+    // a sibling "name copy.ext" through the queue like any copy, undone by removing it
+    pub fn duplicate(&mut self, index: usize) -> Result<()> {
+        let source = match self.files.get(index) {
+            Some(file) => file.path.clone(),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let target = copy_name(&source, |path| self.is_taken(path));
+        let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let op = OpsUnit {
+            operation: Operation::Copy,
+            file_source: source.clone(),
+            file_target: target.clone(),
+        };
+        self.queue.push(format!("Copy {}", name), Task::Copy(source, target), Some(op), None);
+        self.status_info = format!("Queued: {}", name);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    // into a new dir named after the archive, through the queue; undo removes that dir
+    pub fn extract(&mut self, index: usize) -> Result<()> {
+        let (source, format) = match self.files.get(index) {
+            Some(file) if file.is_dir => (file.path.clone(), None),
+            Some(file) => (file.path.clone(), archive::detect(&file.path)),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+        let format = match format {
+            Some(format) => format,
+            None => {
+                self.status_info = "Not an Archive (zip, tar, tar.gz)".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let mut target = archive::dir_name(&source);
+        if self.is_taken(&target) {
+            target = free_name(&target, |path| self.is_taken(path));
+        }
+        let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let op = OpsUnit {
+            operation: Operation::Extract,
+            file_source: source.clone(),
+            file_target: target.clone(),
+        };
+        self.queue.push(format!("Extract {}", name), Task::Extract(source, target, format), Some(op), None);
+        self.status_info = format!("Queued: Extract into {}", name);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    // `name` in the current dir, ".zip", ".tar" or ".tar.gz" by its extension, ".tar.gz" added
+    // when it has none of them; packed through the queue, undo removes the archive
+    pub fn create_archive(&mut self, indices: &[usize], name: &str) -> Result<()> {
+        let sources: Vec<PathBuf> = indices.iter().filter_map(|index| self.files.get(*index)).map(|file| file.path.clone()).collect();
+        if sources.is_empty() {
+            self.status_info = "No File Selected".to_string();
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        let (name, format) = match archive::by_name(name) {
+            Some(format) => (name.to_string(), format),
+            None => (format!("{}.tar.gz", name), archive::Format::TarGz),
+        };
+        if validate_filename(&name).is_err() {
+            self.status_info = "Invalid Name".to_string();
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+        let target = self.current_dir.join(&name);
+        if self.is_taken(&target) {
+            self.status_info = format!("{} Exists", name);
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+
+        let op = OpsUnit {
+            operation: Operation::Archive(sources.clone()),
+            file_source: PathBuf::new(),
+            file_target: target.clone(),
+        };
+        self.queue.push(format!("Pack {}", name), Task::Archive(sources, target, format), Some(op), None);
+        self.status_info = format!("Queued: Pack {}", name);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    pub fn set_mode(&mut self, index: usize, mode: u32) -> Result<()> {
+        let file = match self.files.get(index) {
+            Some(file) => file.path.clone(),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let old_mode = std::fs::metadata(&file)?.permissions().mode();
+        std::fs::set_permissions(&file, Permissions::from_mode(mode))?;
+        let op = OpsUnit {
+            operation: Operation::Chmod(old_mode),
+            file_source: PathBuf::new(),
+            file_target: file,
+        };
+        self.push_history(op);
+
+        self.refresh()?;
+        self.status_info = format!("Mode: {:o} -> {:o}", old_mode & 0o7777, mode & 0o7777);
+        self.status_flag = StatusFlag::Others;
+        Ok(())
+    }
+
+    // chmod u+x or u-x, the listing is read again so the permissions column follows
+    pub fn toggle_executable(&mut self, index: usize) -> Result<()> {
+        let (path, name) = match self.files.get(index) {
+            Some(file) if file.is_dir => {
+                self.status_info = "Dir, Not Changed".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+            Some(file) => (file.path.clone(), file.name.clone()),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let mode = std::fs::metadata(&path)?.permissions().mode() ^ 0o100;
+        self.set_mode(index, mode)?;
+        self.status_info = format!("{}: {}", if mode & 0o100 != 0 { "Executable" } else { "Not Executable" }, name);
+        Ok(())
+    }
+
+    // bump access and modified time to now
+    pub fn touch(&mut self, index: usize) -> Result<()> {
+        let file = match self.files.get(index) {
+            Some(file) => file.path.clone(),
+            None => {
+                self.status_info = "No Selection".to_string();
+                self.status_flag = StatusFlag::Error;
+                return Ok(());
+            }
+        };
+
+        let metadata = std::fs::metadata(&file)?;
+        let (accessed, modified) = (metadata.accessed()?, metadata.modified()?);
+        let now = SystemTime::now();
+        set_file_times(&file, now, now)?;
+        let op = OpsUnit {
+            operation: Operation::Touch(accessed, modified),
+            file_source: PathBuf::new(),
+            file_target: file,
+        };
+        self.push_history(op);
+
         self.refresh()?;
-        self.status_info = format!("Renamed to: {}", new_name);
+        self.status_info = "Touched".to_string();
         self.status_flag = StatusFlag::Others;
         Ok(())
     }
 
     pub fn parent_dir(&mut self) -> Result<()> {
-        if let Some(parent) = self.current_dir.parent() {
+        if let Some(parent) = self.current_dir.parent().map(Path::to_path_buf) {
             let op = OpsUnit {
                 operation: Operation::CD,
                 file_source: self.current_dir.clone(),
-                file_target: parent.to_path_buf(),
+                file_target: parent.clone(),
             };
-            Self::push_history(&mut self.ops_history, op);
-            self.current_dir = parent.to_path_buf();
+            self.push_history(op);
+            self.current_dir = parent;
             self.refresh()?;
         } else {
             self.status_info = "Root Dir".to_string();
             self.status_flag = StatusFlag::Error;
         }
-        self.selected_index = None;
         Ok(())
     }
 
     pub fn sub_dir(&mut self, index: usize) -> Result<()> {
-        let file = match self.selected_index {
-            Some(selected_index) if selected_index == index => self.files.get(index).cloned().unwrap(),
-            _ => {
+        let file = match self.files.get(index) {
+            Some(file) => file.clone(),
+            None => {
                 self.status_info = "No Selection".to_string();
                 self.status_flag = StatusFlag::Error;
                 return Ok(());
@@ -310,10 +1394,30 @@ impl FileSys{
             file_source: self.current_dir.clone(),
             file_target: file.path.clone(),
         };
-        Self::push_history(&mut self.ops_history, op);
+        self.push_history(op);
         self.current_dir = file.path.clone();
         self.refresh()?;
-        self.selected_index = None;
+        Ok(())
+    }
+
+    // jump straight to a directory, `record` decides whether undo can walk back from it
+    pub fn goto_dir(&mut self, target: PathBuf, record: bool) -> Result<()> {
+        if !target.is_dir() {
+            self.status_info = format!("Not Dir: {}", target.display());
+            self.status_flag = StatusFlag::Error;
+            return Ok(());
+        }
+
+        if record {
+            let op = OpsUnit {
+                operation: Operation::CD,
+                file_source: self.current_dir.clone(),
+                file_target: target.clone(),
+            };
+            self.push_history(op);
+        }
+        self.current_dir = target;
+        self.refresh()?;
         Ok(())
     }
 
@@ -327,38 +1431,285 @@ impl FileSys{
             }
         };
 
-        match last_op.operation {
+        // newest first; a step that fails part way is split, what was undone can be redone
+        let mut ops = unbatch(last_op);
+        ops.reverse();
+        let (mut redo, mut left, error) = self.apply_all(ops, Self::revert);
+        if !redo.is_empty() {
+            redo.reverse();
+            Self::push_bounded(&mut self.redo_history, batch_of(redo));
+        }
+        if !left.is_empty() {
+            left.reverse();
+            Self::push_bounded(&mut self.ops_history, batch_of(left));
+        }
+        self.refresh()?;
+        match error {
+            Some(error) => {
+                self.status_info = format!("Undo Failed: {}", error);
+                self.status_flag = StatusFlag::Error;
+            }
+            None => {
+                self.status_info = "Undone".to_string();
+                self.status_flag = StatusFlag::Others;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn redo(&mut self) -> Result<()> {
+        let next_op = match self.redo_history.pop_front() {
+            Some(op) => op,
+            None => {
+                self.status_info = "Nothing to redo".to_string();
+                self.status_flag = StatusFlag::Others;
+                return Ok(());
+            }
+        };
+
+        let next_op = match self.queue_redo(next_op) {
+            Some(op) => op,
+            None => {
+                self.refresh()?;
+                self.status_info = "Redo Queued".to_string();
+                self.status_flag = StatusFlag::Others;
+                return Ok(());
+            }
+        };
+        let (undo, left, error) = self.apply_all(unbatch(next_op), Self::reapply);
+        if !undo.is_empty() {
+            Self::push_bounded(&mut self.ops_history, batch_of(undo));
+        }
+        if !left.is_empty() {
+            Self::push_bounded(&mut self.redo_history, batch_of(left));
+        }
+        self.refresh()?;
+        match error {
+            Some(error) => {
+                self.status_info = format!("Redo Failed: {}", error);
+                self.status_flag = StatusFlag::Error;
+            }
+            None => {
+                self.status_info = "Redone".to_string();
+                self.status_flag = StatusFlag::Others;
+            }
+        }
+        Ok(())
+    }
+
+    // `step` on each op in order until one fails: (what it returned, the failed one and all after it, why)
+    fn apply_all(&mut self, ops: Vec<OpsUnit>, step: fn(&mut Self, OpsUnit) -> Result<OpsUnit>) -> (Vec<OpsUnit>, Vec<OpsUnit>, Option<anyhow::Error>) {
+        let mut done = Vec::with_capacity(ops.len());
+        let mut ops = ops.into_iter();
+        while let Some(op) = ops.next() {
+            match step(self, op.clone()) {
+                Ok(result) => done.push(result),
+                Err(error) => return (done, std::iter::once(op).chain(ops).collect(), Some(error)),
+            }
+        }
+        (done, Vec::new(), None)
+    }
+
+    // returns what redo needs to apply it again
+    fn revert(&mut self, op: OpsUnit) -> Result<OpsUnit> {
+        let operation = match op.operation {
             Operation::Copy => {
-                if last_op.file_target.exists() {
-                    std::fs::remove_file(&last_op.file_target)?;
+                if op.file_target.is_dir() && !op.file_target.is_symlink() {
+                    std::fs::remove_dir_all(&op.file_target)?;
+                } else if op.file_target.exists() {
+                    std::fs::remove_file(&op.file_target)?;
                 }
+                Operation::Copy
             }
             Operation::Cut | Operation::Rename => {
-                if last_op.file_target.exists() {
-                    std::fs::rename(&last_op.file_target, &last_op.file_source)?;
+                if op.file_target.exists() {
+                    std::fs::rename(&op.file_target, &op.file_source)?;
                 }
+                op.operation
             }
-            Operation::New => {
-                if last_op.file_target.exists() {
-                    if last_op.file_target.is_dir() {
-                        std::fs::remove_dir_all(&last_op.file_target)?;
+            Operation::New | Operation::NewDir => {
+                if op.file_target.exists() {
+                    if op.file_target.is_dir() {
+                        std::fs::remove_dir_all(&op.file_target)?;
                     } else {
-                        std::fs::remove_file(&last_op.file_target)?;
+                        std::fs::remove_file(&op.file_target)?;
                     }
                 }
+                op.operation
             }
             Operation::CD => {
-                self.current_dir = last_op.file_source;
-                self.refresh()?;
+                self.current_dir = op.file_source.clone();
+                Operation::CD
+            }
+            // setting a mode or times back is its own inverse, it just remembers the other value
+            Operation::Chmod(_) | Operation::Touch(_, _) => return swap_attributes(op),
+            Operation::Extract => {
+                if op.file_target.is_dir() {
+                    std::fs::remove_dir_all(&op.file_target)?;
+                }
+                Operation::Extract
+            }
+            Operation::Archive(sources) => {
+                if op.file_target.exists() {
+                    std::fs::remove_file(&op.file_target)?;
+                }
+                Operation::Archive(sources)
+            }
+            // only the link goes, never what it points at
+            Operation::Symlink => {
+                if op.file_target.is_symlink() {
+                    std::fs::remove_file(&op.file_target)?;
+                }
+                Operation::Symlink
+            }
+            Operation::RemoveDir => {
+                if !op.file_source.exists() {
+                    std::fs::create_dir(&op.file_source)?;
+                }
+                Operation::RemoveDir
+            }
+            Operation::Delete => {
+                let trash = self.trash.as_ref().ok_or_else(|| anyhow!("Trash not available"))?;
+                trash.restore(&op.file_target, &op.file_source)?;
+                Operation::Delete
             }
+            Operation::Batch(ops) => {
+                let mut redo = Vec::with_capacity(ops.len());
+                for op in ops.into_iter().rev() {
+                    redo.push(self.revert(op)?);
+                }
+                redo.reverse();
+                Operation::Batch(redo)
+            }
+        };
+        Ok(OpsUnit { operation, ..op })
+    }
+
+    // slow ones go to the queue like the first time and join the history once done, anything else is handed back
+    fn queue_redo(&mut self, op: OpsUnit) -> Option<OpsUnit> {
+        // a copy that overwrote something comes with its backup, a new one is made below
+        let op = match op.operation {
+            Operation::Batch(mut ops) if ops.len() == 2
+                && matches!(ops[0].operation, Operation::Rename)
+                && matches!(ops[1].operation, Operation::Copy) => ops.pop().unwrap(),
+            operation => OpsUnit { operation, ..op },
+        };
+        let name = op.file_target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match op.operation {
+            Operation::Copy => {
+                // whatever has the name now is moved aside, the way an overwriting paste does
+                let backup = match op.file_target.symlink_metadata() {
+                    Ok(_) => match backup_existing(&op.file_target) {
+                        Ok(backup) => Some(backup),
+                        Err(_) => return Some(op),
+                    },
+                    Err(_) => None,
+                };
+                let task = Task::Copy(op.file_source.clone(), op.file_target.clone());
+                self.queue.push_redo(format!("Copy {}", name), task, op, backup);
+                None
+            }
+            Operation::Extract => match archive::detect(&op.file_source) {
+                Some(format) => {
+                    let task = Task::Extract(op.file_source.clone(), op.file_target.clone(), format);
+                    self.queue.push_redo(format!("Extract {}", name), task, op, None);
+                    None
+                }
+                None => Some(op),
+            },
+            Operation::Archive(ref sources) => {
+                let format = archive::by_name(&name).unwrap_or(archive::Format::TarGz);
+                let task = Task::Archive(sources.clone(), op.file_target.clone(), format);
+                self.queue.push_redo(format!("Pack {}", name), task, op, None);
+                None
+            }
+            _ => Some(op),
         }
-        self.refresh()?;
-        self.status_info = "Undone".to_string();
-        self.status_flag = StatusFlag::Others;
-        Ok(())
     }
 
-    fn push_history(target: &mut VecDeque<OpsUnit>, ops: OpsUnit){
+    // the forward direction of `revert`, returns what undo needs
+    fn reapply(&mut self, op: OpsUnit) -> Result<OpsUnit> {
+        let operation = match op.operation {
+            Operation::Cut | Operation::Rename => {
+                if op.file_target.exists() {
+                    return Err(anyhow!("{} exists", op.file_target.display()));
+                }
+                std::fs::rename(&op.file_source, &op.file_target)?;
+                op.operation
+            }
+            Operation::New => {
+                std::fs::OpenOptions::new().write(true).create_new(true).open(&op.file_target)?;
+                Operation::New
+            }
+            Operation::NewDir => {
+                std::fs::create_dir(&op.file_target)?;
+                Operation::NewDir
+            }
+            Operation::CD => {
+                self.current_dir = op.file_target.clone();
+                Operation::CD
+            }
+            Operation::Chmod(_) | Operation::Touch(_, _) => return swap_attributes(op),
+            Operation::Symlink => {
+                std::os::unix::fs::symlink(&op.file_source, &op.file_target)?;
+                Operation::Symlink
+            }
+            // `queue_redo` takes these, an extract only stays behind when the archive is gone
+            Operation::Extract => return Err(anyhow!("{} is gone", op.file_source.display())),
+            Operation::Copy | Operation::Archive(_) => return Err(anyhow!("Can not redo {}", op.file_target.display())),
+            Operation::RemoveDir => {
+                std::fs::remove_dir(&op.file_source)?;
+                Operation::RemoveDir
+            }
+            Operation::Delete => {
+                let trash = self.trash.as_ref().ok_or_else(|| anyhow!("Trash not available"))?;
+                // may land under another name than the first time
+                let target = trash.put(&op.file_source)?;
+                return Ok(OpsUnit { file_target: target, ..op });
+            }
+            Operation::Batch(ops) => {
+                let mut undo = Vec::with_capacity(ops.len());
+                for op in ops {
+                    undo.push(self.reapply(op)?);
+                }
+                Operation::Batch(undo)
+            }
+        };
+        Ok(OpsUnit { operation, ..op })
+    }
+
+    // where paste and new entries land: the marked dir when it is the only mark, otherwise the current one
+    pub fn target_dir(&self) -> PathBuf {
+        match self.single_marked().and_then(|index| self.files.get(index)) {
+            Some(file) if file.is_dir => file.path.clone(),
+            _ => self.current_dir.clone(),
+        }
+    }
+
+    // on disk already, or about to be created by a queued job
+    fn is_taken(&self, path: &Path) -> bool {
+        path.symlink_metadata().is_ok() || self.queue.claims(path)
+    }
+
+    // a protected path itself, anything containing one, or anything below a "/**" entry
+    fn is_protected(&self, path: &Path) -> bool {
+        // resolve the parent only, a symlink is judged as the link and not its target
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => parent.canonicalize().map(|parent| parent.join(name)).unwrap_or_else(|_| path.to_path_buf()),
+            _ => path.to_path_buf(),
+        };
+        self.protected.iter().any(|(protected, subtree)| {
+            protected.starts_with(&path) || (*subtree && path.starts_with(protected))
+        })
+    }
+
+    // a fresh operation makes whatever was undone before unreachable
+    fn push_history(&mut self, ops: OpsUnit){
+        self.redo_history.clear();
+        Self::push_bounded(&mut self.ops_history, ops);
+    }
+
+    fn push_bounded(target: &mut VecDeque<OpsUnit>, ops: OpsUnit){
         if target.len() == MAX_HISTORY_SIZE {
             target.pop_back();
         }
@@ -369,18 +1720,426 @@ impl FileSys{
     pub fn current_dir(&self) -> &PathBuf { &self.current_dir }
     pub fn status_info(&self) -> &str { &self.status_info }
     pub fn status_flag(&self) -> StatusFlag { self.status_flag }
-    pub fn selected_index(&self) -> Option<usize> { self.selected_index }
+    pub fn is_marked(&self, index: usize) -> bool { self.marked.contains(&index) }
+    pub fn marked_count(&self) -> usize { self.marked.len() }
+    pub fn sort_mode(&self) -> SortMode { self.sort_mode }
+    pub fn sort_reversed(&self) -> bool { self.reversed }
+    pub fn queue(&self) -> &OpsQueue { &self.queue }
+    pub fn uses_trash(&self) -> bool { self.trash.is_some() }
+    pub fn git_status(&self) -> bool { self.git_status }
+    pub fn hide_ignored(&self) -> bool { self.hide_ignored }
+    pub fn git_state(&self, name: &str) -> Option<GitState> { self.git_states.get(name).copied() }
+    pub fn is_git_ignored(&self, name: &str) -> bool { self.git_ignored.contains(name) }
+    pub fn clipboard_history(&self) -> &VecDeque<(PathBuf, bool)> { &self.clipboard_history }
 }
 
+fn parse_protected(entry: &str) -> (PathBuf, bool) {
+    match entry.strip_suffix("/**") {
+        Some(root) => (expand_home(root), true),
+        None => (expand_home(entry), false),
+    }
+}
+
+// moves an existing `target` aside to "name~" (then "name~1", ...) so it can be overwritten
+fn backup_existing(target: &Path) -> Result<OpsUnit> {
+    let mut suffix = 0;
+    let backup = loop {
+        let mut name = target.as_os_str().to_os_string();
+        name.push(if suffix == 0 { "~".to_string() } else { format!("~{}", suffix) });
+        let candidate = PathBuf::from(name);
+        if candidate.symlink_metadata().is_err() {
+            break candidate;
+        }
+        suffix += 1;
+    };
+
+    std::fs::rename(target, &backup)?;
+    Ok(OpsUnit {
+        operation: Operation::Rename,
+        file_source: target.to_path_buf(),
+        file_target: backup,
+    })
+}
+
+// "name (1).ext", "name (2).ext", ... whichever is free first
+fn free_name(taken: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = taken.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = taken.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    let mut counter = 1;
+    loop {
+        let candidate = taken.with_file_name(format!("{} ({}){}", stem, counter, ext));
+        if !is_taken(&candidate) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+// "a.txt" -> "a copy.txt", then "a copy 2.txt" and so on; a dir keeps its dots
+fn copy_name(source: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let (stem, ext) = match (source.file_stem(), source.extension()) {
+        (Some(stem), Some(ext)) if !source.is_dir() => (stem.to_string_lossy().into_owned(), format!(".{}", ext.to_string_lossy())),
+        _ => (source.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(), String::new()),
+    };
+    let mut candidate = source.with_file_name(format!("{} copy{}", stem, ext));
+    let mut counter = 2;
+    while is_taken(&candidate) {
+        candidate = source.with_file_name(format!("{} copy {}{}", stem, counter, ext));
+        counter += 1;
+    }
+    candidate
+}
+
+// the overwrite failed, put the original back where it was
+fn restore_backup(backup: &Option<OpsUnit>) {
+    if let Some(backup) = backup {
+        let _ = std::fs::rename(&backup.file_target, &backup.file_source);
+    }
+}
+
+fn backup_name(backup: &Option<OpsUnit>) -> String {
+    match backup.as_ref().and_then(|backup| backup.file_target.file_name()) {
+        Some(name) => format!(" (backup: {})", name.to_string_lossy()),
+        None => String::new(),
+    }
+}
+
+// undone as one step: the new file goes, then the backup comes back
+fn with_backup(op: OpsUnit, backup: Option<OpsUnit>) -> OpsUnit {
+    match backup {
+        Some(backup) => OpsUnit {
+            operation: Operation::Batch(vec![backup, op]),
+            file_source: PathBuf::new(),
+            file_target: PathBuf::new(),
+        },
+        None => op,
+    }
+}
+
+// the ops of a step in the order they were done, nested batches included
+fn unbatch(op: OpsUnit) -> Vec<OpsUnit> {
+    match op.operation {
+        Operation::Batch(ops) => ops.into_iter().flat_map(unbatch).collect(),
+        _ => vec![op],
+    }
+}
+
+// several ops as one undo step, a single one stays as it is
+fn batch_of(mut ops: Vec<OpsUnit>) -> OpsUnit {
+    if ops.len() == 1 {
+        return ops.pop().unwrap();
+    }
+    OpsUnit {
+        operation: Operation::Batch(ops),
+        file_source: PathBuf::new(),
+        file_target: PathBuf::new(),
+    }
+}
+
+// "name" for one entry, "3 entries" for more
+fn describe(files: &[FileInfo]) -> String {
+    match files {
+        [file] => file.name.clone(),
+        _ => format!("{} entries", files.len()),
+    }
+}
+
+// copies a file or a whole tree, returns how many entries were written;
+// links are recreated as links, so one pointing back into the tree cannot loop
+pub fn copy_recursive(source: &Path, target: &Path) -> std::io::Result<usize> {
+    copy_tracked(source, target, &mut |_| true)
+}
+
+// `progress` hears about every chunk written (in bytes); returning false stops the copy
+// with ErrorKind::Interrupted, whatever was copied so far stays for the caller to clean up
+pub fn copy_tracked(source: &Path, target: &Path, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<usize> {
+    let metadata = source.symlink_metadata()?;
+    if metadata.file_type().is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(source)?, target)?;
+        return Ok(1);
+    }
+    if !metadata.is_dir() {
+        copy_file_chunked(source, target, progress)?;
+        std::fs::set_permissions(target, metadata.permissions())?;
+        return Ok(1);
+    }
+
+    std::fs::create_dir(target)?;
+    let mut count = 1;
+    for entry in read_dir(source)? {
+        let entry = entry?;
+        count += copy_tracked(&entry.path(), &target.join(entry.file_name()), progress)?;
+    }
+    Ok(count)
+}
+
+fn copy_file_chunked(source: &Path, target: &Path, progress: &mut dyn FnMut(u64) -> bool) -> std::io::Result<()> {
+    let mut reader = std::fs::File::open(source)?;
+    let mut writer = std::fs::OpenOptions::new().write(true).create_new(true).open(target)?;
+    let mut buffer = vec![0; COPY_CHUNK];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        writer.write_all(&buffer[..read])?;
+        if !progress(read as u64) {
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "Cancelled"));
+        }
+    }
+}
+
+// bytes a copy of `path` will write, links count as nothing
+pub fn tree_size(path: &Path) -> u64 {
+    let metadata = match path.symlink_metadata() {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return if metadata.file_type().is_symlink() { 0 } else { metadata.len() };
+    }
+    read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| tree_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+// puts the stored mode or times on the file, the replaced values come back for the other direction
+fn swap_attributes(op: OpsUnit) -> Result<OpsUnit> {
+    let metadata = std::fs::metadata(&op.file_target)?;
+    let operation = match op.operation {
+        Operation::Chmod(mode) => {
+            std::fs::set_permissions(&op.file_target, Permissions::from_mode(mode))?;
+            Operation::Chmod(metadata.permissions().mode())
+        }
+        Operation::Touch(accessed, modified) => {
+            set_file_times(&op.file_target, accessed, modified)?;
+            Operation::Touch(metadata.accessed()?, metadata.modified()?)
+        }
+        other => other,
+    };
+    Ok(OpsUnit { operation, ..op })
+}
+
+// by path, the file is never opened: one we may write but not read gets its times all the same
+fn set_file_times(path: &Path, accessed: SystemTime, modified: SystemTime) -> Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let times = [timespec(accessed), timespec(modified)];
+    if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+// times before 1970 are clamped to it
+fn timespec(time: SystemTime) -> libc::timespec {
+    let since = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    libc::timespec { tv_sec: since.as_secs() as libc::time_t, tv_nsec: since.subsec_nanos() as libc::c_long }
+}
+
+fn compare_files(mode: SortMode, a: &FileInfo, b: &FileInfo) -> Ordering {
+    match mode {
+        SortMode::Name => a.name.cmp(&b.name),
+        SortMode::Size => b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)),
+        SortMode::Modified => b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)),
+        SortMode::ExtModified => extension_of(a).cmp(&extension_of(b))
+            .then_with(|| b.modified.cmp(&a.modified))
+            .then_with(|| a.name.cmp(&b.name)),
+        SortMode::NameLength => a.name.chars().count().cmp(&b.name.chars().count())
+            .then_with(|| a.name.cmp(&b.name)),
+        SortMode::Type => extension_of(a).cmp(&extension_of(b))
+            .then_with(|| a.name.cmp(&b.name)),
+    }
+}
+
+// lowercase so "JPG" and "jpg" group together, no extension sorts first
+pub fn extension_of(file: &FileInfo) -> Option<String> {
+    file.path.extension().map(|ext| ext.to_string_lossy().to_lowercase())
+}
+
+// {n} is zero padded to `width` so the results sort in order
+fn expand_rename_pattern(pattern: &str, source: &Path, counter: usize, width: usize) -> String {
+    let stem = source.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = source.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    pattern
+        .replace("{n}", &format!("{:0width$}", counter, width = width))
+        .replace("{name}", &stem)
+        .replace("{ext}", &ext)
+}
+
+// "a/b/c" below the current dir: every component a valid name, so no "..", no leading "/"
+fn validate_relative_path(path: &str) -> Result<(), ()> {
+    if path.starts_with('/') {
+        return Err(());
+    }
+    let mut components = path.split('/').filter(|component| !component.is_empty()).peekable();
+    if components.peek().is_none() {
+        return Err(());
+    }
+    components.try_for_each(validate_filename)
+}
+
+// a single name in the current dir; ".." only matters as the whole name, "a..b" is fine
 fn validate_filename(name: &str) -> Result<(), ()> {
     if name.is_empty()
+        || name == "."
+        || name == ".."
         || name.contains('/')
         || name.contains('\\')
-        || name.contains("..")
         || name.contains('\0')
+        || name.len() > 255 // NAME_MAX, in bytes
     {
         Err(())
     } else {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_info::test_dir::TestDir;
+
+    fn listed(dir: &TestDir) -> FileSys {
+        FileSys::init(dir.path().to_path_buf(), &Config::default()).unwrap()
+    }
+
+    fn index_of(fs: &FileSys, name: &str) -> usize {
+        fs.files().iter().position(|file| file.name == name).unwrap()
+    }
+
+    // until the queue is idle and its results were taken in
+    fn wait_for_jobs(fs: &mut FileSys) {
+        for _ in 0..500 {
+            if fs.poll_jobs().unwrap() && !fs.queue().is_busy() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("queue did not finish");
+    }
+
+    #[test]
+    fn validate_filename_rejects_what_can_not_be_a_name() {
+        for bad in ["", ".", "..", "a/b", "/", "a\\b", "a\0b", &"x".repeat(256)] {
+            assert!(validate_filename(bad).is_err(), "{:?}", bad);
+        }
+        for good in ["a", ".hidden", "a..b", "...", "name with spaces.txt", &"x".repeat(255)] {
+            assert!(validate_filename(good).is_ok(), "{:?}", good);
+        }
+    }
+
+    #[test]
+    fn redo_brings_back_an_undone_new_file() {
+        let dir = TestDir::new();
+        let mut fs = listed(&dir);
+        let path = dir.path().join("a.txt");
+
+        fs.new_file("a.txt", false).unwrap();
+        assert!(path.is_file());
+        fs.undo().unwrap();
+        assert!(!path.exists());
+        fs.redo().unwrap();
+        assert!(path.is_file());
+        assert_eq!(fs.status_info(), "Redone");
+    }
+
+    #[test]
+    fn redo_queues_an_undone_copy_again() {
+        let dir = TestDir::new();
+        dir.file("a.txt", "content");
+        let mut fs = listed(&dir);
+        let copy = dir.path().join("a copy.txt");
+
+        fs.duplicate(index_of(&fs, "a.txt")).unwrap();
+        wait_for_jobs(&mut fs);
+        fs.undo().unwrap();
+        assert!(!copy.exists());
+        fs.redo().unwrap();
+        assert_eq!(fs.status_info(), "Redo Queued");
+        wait_for_jobs(&mut fs);
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "content");
+        // back in the history, undo removes it again
+        fs.undo().unwrap();
+        assert!(!copy.exists());
+    }
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn undo_puts_back_the_mode_before_chmod() {
+        let dir = TestDir::new();
+        let path = dir.file("run.sh", "");
+        std::fs::set_permissions(&path, Permissions::from_mode(0o644)).unwrap();
+        let mut fs = listed(&dir);
+
+        fs.toggle_executable(index_of(&fs, "run.sh")).unwrap();
+        assert_eq!(mode(&path), 0o744);
+        fs.undo().unwrap();
+        assert_eq!(mode(&path), 0o644);
+        fs.redo().unwrap();
+        assert_eq!(mode(&path), 0o744);
+    }
+
+    #[test]
+    fn undo_puts_back_the_times_before_touch() {
+        let dir = TestDir::new();
+        let path = dir.file("old.txt", "");
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        set_file_times(&path, old, old).unwrap();
+        let mut fs = listed(&dir);
+
+        fs.touch(index_of(&fs, "old.txt")).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().modified().unwrap() > old);
+        fs.undo().unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), old);
+        assert_eq!(metadata.accessed().unwrap(), old);
+    }
+
+    #[test]
+    fn touch_sets_times_on_a_write_only_file() {
+        let dir = TestDir::new();
+        let path = dir.file("drop.log", "");
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        set_file_times(&path, old, old).unwrap();
+        std::fs::set_permissions(&path, Permissions::from_mode(0o200)).unwrap();
+        let mut fs = listed(&dir);
+
+        fs.touch(index_of(&fs, "drop.log")).unwrap();
+        assert_eq!(fs.status_info(), "Touched");
+        assert!(std::fs::metadata(&path).unwrap().modified().unwrap() > old);
+    }
+
+    #[test]
+    fn broken_link_is_listed_as_one() {
+        let dir = TestDir::new();
+        dir.file("real.txt", "x");
+        std::os::unix::fs::symlink("real.txt", dir.path().join("good")).unwrap();
+        std::os::unix::fs::symlink("missing.txt", dir.path().join("dangling")).unwrap();
+        let fs = listed(&dir);
+
+        let dangling = &fs.files()[index_of(&fs, "dangling")];
+        assert!(dangling.broken_link && !dangling.unreadable);
+        assert_eq!(dangling.link_target.as_deref(), Some(Path::new("missing.txt")));
+        let good = &fs.files()[index_of(&fs, "good")];
+        assert!(!good.broken_link);
+        assert_eq!(good.size, 1);
+    }
+
+    #[test]
+    fn refresh_of_a_removed_dir_moves_up_to_what_is_left() {
+        let dir = TestDir::new();
+        let deep = dir.file("a/b/c/x.txt", "").parent().unwrap().to_path_buf();
+        let mut fs = FileSys::init(deep, &Config::default()).unwrap();
+        std::fs::remove_dir_all(dir.path().join("a/b")).unwrap();
+
+        fs.refresh().unwrap();
+        assert_eq!(fs.current_dir(), &dir.path().join("a"));
+        assert!(fs.status_info().contains("Gone, Moved Up to"));
+        assert!(fs.files().is_empty());
+    }
+}
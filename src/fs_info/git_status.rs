@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// ordered by how much it matters, a dir shows the loudest state found below it
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum GitState {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+}
+
+impl GitState {
+    pub fn glyph(self) -> &'static str {
+        match self {
+            GitState::Ignored => "!",
+            GitState::Untracked => "?",
+            GitState::Staged => "+",
+            GitState::Modified => "M",
+        }
+    }
+}
+
+// top of the repo `dir` is in, None outside one or without git
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    git(dir, &["rev-parse", "--show-toplevel"]).map(|output| PathBuf::from(output.trim_end()))
+}
+
+// state of each entry of `dir` (inside the repo at `root`) by name, clean entries are left out;
+// and the entries git reports as ignored themselves, not just for something ignored below them
+pub fn load(dir: &Path, root: &Path) -> (HashMap<String, GitState>, HashSet<String>) {
+    let mut states = HashMap::new();
+    let mut ignored = HashSet::new();
+    // paths in porcelain output are relative to the repo root, whatever the cwd
+    let output = match git(dir, &["status", "--porcelain", "-z", "--ignored", "--", "."]) {
+        Some(output) => output,
+        None => return (states, ignored),
+    };
+
+    // git reports the real path of the root, compare against ours resolved the same way
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        if field.len() < 4 {
+            continue;
+        }
+        let (code, path) = field.split_at(3);
+        let mut code = code.chars();
+        let (index, worktree) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+        // renames and copies carry the old path as an extra field
+        if matches!(index, 'R' | 'C') {
+            fields.next();
+        }
+
+        let state = match (index, worktree) {
+            ('?', '?') => GitState::Untracked,
+            ('!', '!') => GitState::Ignored,
+            (_, ' ') => GitState::Staged,
+            _ => GitState::Modified,
+        };
+        let full = root.join(path.trim_end_matches('/'));
+        let rest = match full.strip_prefix(&dir) {
+            Ok(rest) => rest,
+            Err(_) => continue,
+        };
+        let mut components = rest.components();
+        let name = match components.next() {
+            Some(name) => name.as_os_str().to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let below = components.next().is_some();
+        if state == GitState::Ignored {
+            // a build artifact inside a tracked dir leaves the dir as it is
+            if !below {
+                states.insert(name.clone(), state);
+                ignored.insert(name);
+            }
+            continue;
+        }
+        let entry = states.entry(name).or_insert(state);
+        *entry = (*entry).max(state);
+    }
+    (states, ignored)
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_info::test_dir::TestDir;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-C"])
+            .arg(dir)
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn ignored_artifact_leaves_its_tracked_dir_alone() {
+        let dir = TestDir::new();
+        dir.file(".gitignore", "*.o\n");
+        dir.file("src/foo.c", "");
+        dir.file("src/foo.o", "");
+        dir.file("build.o", "");
+        run_git(dir.path(), &["init", "-q"]);
+        run_git(dir.path(), &["add", "."]);
+        run_git(dir.path(), &["commit", "-q", "-m", "init"]);
+        let root = repo_root(dir.path()).unwrap();
+
+        let (states, ignored) = load(dir.path(), &root);
+        assert!(!states.contains_key("src"));
+        assert!(!ignored.contains("src"));
+        assert!(ignored.contains("build.o"));
+
+        let (states, ignored) = load(&dir.path().join("src"), &root);
+        assert!(states.get("foo.o") == Some(&GitState::Ignored));
+        assert!(ignored.contains("foo.o") && !ignored.contains("foo.c"));
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use crate::fs_info::archive::{self, Format};
+use crate::fs_info::file_ops::OpsUnit;
+use crate::fs_info::file_system_info::{copy_tracked, tree_size};
+
+// the slow part of an operation, everything that can be checked up front already was
+pub enum Task {
+    Copy(PathBuf, PathBuf), // (source, target)
+    Delete(PathBuf),
+    DeleteAll(Vec<PathBuf>), // keeps going past entries that fail, reported together at the end
+    Extract(PathBuf, PathBuf, Format), // (archive, new dir to unpack into)
+    Archive(Vec<PathBuf>, PathBuf, Format), // (sources, new archive file)
+}
+
+impl Task {
+    // the path it creates, None for deletes
+    fn target(&self) -> Option<&Path> {
+        match self {
+            Task::Copy(_, target) | Task::Extract(_, target, _) | Task::Archive(_, target, _) => Some(target),
+            Task::Delete(_) | Task::DeleteAll(_) => None,
+        }
+    }
+}
+
+pub struct Job {
+    pub id: u64,
+    pub label: String, // shown in the status bar and the queue view
+    pub task: Task,
+    pub undo: Option<OpsUnit>,   // goes to the history once the task succeeded
+    pub backup: Option<OpsUnit>, // overwritten target, put back if the task fails
+    pub redone: bool, // from redo: the redo history past it stays, a failure puts it back there
+}
+
+#[derive(Default)]
+struct Shared {
+    pending: VecDeque<Job>,
+    running: Option<String>, // label of the job the worker is on
+    running_target: Option<PathBuf>, // what the running job creates, still claimed until it is done
+    progress: Option<(u64, u64)>, // (bytes done, bytes total) of a running copy or (un)packing
+    stop: bool, // asked to abandon the running job
+}
+
+// one worker, jobs run in the order they were queued
+pub struct OpsQueue {
+    shared: Arc<(Mutex<Shared>, Condvar)>,
+    finished: Receiver<(Job, Result<usize, String>)>,
+    next_id: u64,
+}
+
+impl OpsQueue {
+    pub fn new() -> Self {
+        let shared = Arc::new((Mutex::new(Shared::default()), Condvar::new()));
+        let (sender, finished) = mpsc::channel();
+
+        let worker_shared = Arc::clone(&shared);
+        thread::spawn(move || work(worker_shared, sender));
+
+        OpsQueue {
+            shared,
+            finished,
+            next_id: 0,
+        }
+    }
+
+    pub fn push(&mut self, label: String, task: Task, undo: Option<OpsUnit>, backup: Option<OpsUnit>) {
+        self.enqueue(Job { id: 0, label, task, undo, backup, redone: false });
+    }
+
+    pub fn push_redo(&mut self, label: String, task: Task, undo: OpsUnit, backup: Option<OpsUnit>) {
+        self.enqueue(Job { id: 0, label, task, undo: Some(undo), backup, redone: true });
+    }
+
+    fn enqueue(&mut self, mut job: Job) {
+        self.next_id += 1;
+        job.id = self.next_id;
+
+        let (lock, wake) = &*self.shared;
+        lock.lock().unwrap().pending.push_back(job);
+        wake.notify_one();
+    }
+
+    // jobs the worker has not started yet can be dropped, the running one has to finish
+    pub fn cancel(&mut self, id: u64) -> Option<Job> {
+        let mut shared = self.shared.0.lock().unwrap();
+        let position = shared.pending.iter().position(|job| job.id == id)?;
+        shared.pending.remove(position)
+    }
+
+    // (running label, [(id, label)] still waiting)
+    pub fn snapshot(&self) -> (Option<String>, Vec<(u64, String)>) {
+        let shared = self.shared.0.lock().unwrap();
+        let pending = shared.pending.iter().map(|job| (job.id, job.label.clone())).collect();
+        (shared.running.clone(), pending)
+    }
+
+    pub fn progress(&self) -> Option<(u64, u64)> {
+        self.shared.0.lock().unwrap().progress
+    }
+
+    // only copies and (un)packing check for this, false when none is running
+    pub fn stop_running(&self) -> bool {
+        let mut shared = self.shared.0.lock().unwrap();
+        if shared.progress.is_none() {
+            return false;
+        }
+        shared.stop = true;
+        true
+    }
+
+    // a queued or running job is going to create this path
+    pub fn claims(&self, path: &Path) -> bool {
+        let shared = self.shared.0.lock().unwrap();
+        shared.running_target.as_deref() == Some(path)
+            || shared.pending.iter().any(|job| job.task.target() == Some(path))
+    }
+
+    pub fn is_busy(&self) -> bool {
+        let shared = self.shared.0.lock().unwrap();
+        shared.running.is_some() || !shared.pending.is_empty()
+    }
+
+    // results since the last call, never blocks
+    pub fn finished(&self) -> Vec<(Job, Result<usize, String>)> {
+        self.finished.try_iter().collect()
+    }
+}
+
+fn work(shared: Arc<(Mutex<Shared>, Condvar)>, sender: Sender<(Job, Result<usize, String>)>) {
+    let (lock, wake) = &*shared;
+    loop {
+        let job = {
+            let mut state = lock.lock().unwrap();
+            while state.pending.is_empty() {
+                state = wake.wait(state).unwrap();
+            }
+            let job = state.pending.pop_front().unwrap();
+            state.running = Some(job.label.clone());
+            state.running_target = job.task.target().map(Path::to_path_buf);
+            state.stop = false;
+            job
+        };
+
+        // Ok holds how many entries were handled
+        let result = match &job.task {
+            Task::Copy(source, target) => {
+                // something showed up there since it was queued, it is not ours to overwrite
+                if target.symlink_metadata().is_ok() {
+                    Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{} exists", target.display())))
+                } else {
+                    lock.lock().unwrap().progress = Some((0, tree_size(source)));
+                    copy_tracked(source, target, &mut |bytes| report(lock, bytes)).inspect_err(|_| {
+                        // no half copied trees left behind
+                        let is_dir = target.symlink_metadata().map(|metadata| metadata.is_dir()).unwrap_or(false);
+                        let _ = if is_dir { std::fs::remove_dir_all(target) } else { std::fs::remove_file(target) };
+                    })
+                }
+            }
+            Task::Extract(source, target, format) => {
+                let total = source.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                lock.lock().unwrap().progress = Some((0, total));
+                // cleans up after itself, but only what it created
+                archive::extract(source, target, *format, &mut |bytes| report(lock, bytes))
+            }
+            Task::Archive(sources, target, format) => {
+                lock.lock().unwrap().progress = Some((0, sources.iter().map(|source| tree_size(source)).sum()));
+                archive::create(sources, target, *format, &mut |bytes| report(lock, bytes))
+            }
+            Task::Delete(path) => remove(path).map(|_| 1),
+            Task::DeleteAll(paths) => {
+                let failed: Vec<String> = paths
+                    .iter()
+                    .filter_map(|path| remove(path).err().map(|error| {
+                        format!("{} ({})", path.file_name().unwrap_or_default().to_string_lossy(), error)
+                    }))
+                    .collect();
+                if failed.is_empty() {
+                    Ok(paths.len())
+                } else {
+                    let deleted = paths.len() - failed.len();
+                    Err(std::io::Error::other(format!("{} of {} deleted, failed: {}", deleted, paths.len(), failed.join(", "))))
+                }
+            }
+        };
+
+        {
+            let mut state = lock.lock().unwrap();
+            state.running = None;
+            state.running_target = None;
+            state.progress = None;
+        }
+        // the UI is gone, nobody is left to tell
+        if sender.send((job, result.map_err(|error| error.to_string()))).is_err() {
+            return;
+        }
+    }
+}
+
+// adds to the running job's progress, false once it was asked to stop
+fn report(lock: &Mutex<Shared>, bytes: u64) -> bool {
+    let mut state = lock.lock().unwrap();
+    if let Some((done, _)) = state.progress.as_mut() {
+        *done += bytes;
+    }
+    !state.stop
+}
+
+fn remove(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+// uid/gid -> name from /etc/passwd and /etc/group, read once instead of per file;
+// ids only known to NSS (LDAP, sssd, ...) are asked for once and remembered, misses too
+pub struct OwnerNames {
+    users: RefCell<HashMap<u32, String>>,
+    groups: RefCell<HashMap<u32, String>>,
+}
+
+impl OwnerNames {
+    pub fn load() -> Self {
+        OwnerNames {
+            users: RefCell::new(read_id_file("/etc/passwd")),
+            groups: RefCell::new(read_id_file("/etc/group")),
+        }
+    }
+
+    // numeric id when there is no name for it
+    pub fn user(&self, uid: u32) -> String {
+        self.users.borrow_mut()
+            .entry(uid)
+            .or_insert_with(|| lookup_user(uid).unwrap_or_else(|| uid.to_string()))
+            .clone()
+    }
+
+    pub fn group(&self, gid: u32) -> String {
+        self.groups.borrow_mut()
+            .entry(gid)
+            .or_insert_with(|| lookup_group(gid).unwrap_or_else(|| gid.to_string()))
+            .clone()
+    }
+
+    // accepts a user name or a numeric uid
+    pub fn uid_of(&self, user: &str) -> Option<u32> {
+        user.parse().ok().or_else(|| {
+            self.users.borrow().iter()
+                .find(|(_, name)| name.as_str() == user)
+                .map(|(uid, _)| *uid)
+        })
+    }
+}
+
+// both files are "name:password:id:..." lines
+fn read_id_file(path: &str) -> HashMap<u32, String> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+fn lookup_user(uid: u32) -> Option<String> {
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let code = unsafe { libc::getpwuid_r(uid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if code != 0 || result.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(entry.pw_name) }.to_string_lossy().into_owned())
+}
+
+fn lookup_group(gid: u32) -> Option<String> {
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    let code = unsafe { libc::getgrgid_r(gid, &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if code != 0 || result.is_null() {
+        return None;
+    }
+    Some(unsafe { CStr::from_ptr(entry.gr_name) }.to_string_lossy().into_owned())
+}
+
+pub fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
@@ -0,0 +1,104 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::fs_info::file_info::local_time;
+
+// the home trash of the freedesktop.org trash spec, $XDG_DATA_HOME/Trash
+pub struct Trash {
+    files: PathBuf,
+    info: PathBuf,
+}
+
+impl Trash {
+    pub fn locate() -> Option<Trash> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+        let root = data_home.join("Trash");
+        Some(Trash {
+            files: root.join("files"),
+            info: root.join("info"),
+        })
+    }
+
+    // moves `path` into the trash and returns where it ended up;
+    // a rename cannot leave the filesystem, that error is passed on untouched (EXDEV)
+    pub fn put(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.files)?;
+        std::fs::create_dir_all(&self.info)?;
+        let original = std::path::absolute(path)?;
+        let name = original.file_name().ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+
+        // the .trashinfo file is the lock on the name, whoever creates it first owns it;
+        // an entry in files/ left without its info by some other tool takes the name too
+        let mut suffix = 1;
+        let (trashed, info) = loop {
+            let mut candidate = name.to_os_string();
+            if suffix > 1 {
+                candidate.push(format!(".{}", suffix));
+            }
+            let mut info_name = candidate.clone();
+            info_name.push(".trashinfo");
+            let info = self.info.join(info_name);
+
+            match OpenOptions::new().write(true).create_new(true).open(&info) {
+                Ok(_) if self.files.join(&candidate).symlink_metadata().is_ok() => {
+                    let _ = std::fs::remove_file(&info);
+                    suffix += 1;
+                }
+                Ok(mut file) => {
+                    write!(file, "[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(&original), local_timestamp())?;
+                    break (self.files.join(candidate), info);
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => suffix += 1,
+                Err(error) => return Err(error),
+            }
+        };
+
+        if let Err(error) = std::fs::rename(&original, &trashed) {
+            let _ = std::fs::remove_file(&info);
+            return Err(error);
+        }
+        Ok(trashed)
+    }
+
+    // back to where it was deleted from, refuses to overwrite what has taken its place since
+    pub fn restore(&self, trashed: &Path, original: &Path) -> io::Result<()> {
+        if original.symlink_metadata().is_ok() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+        }
+        std::fs::rename(trashed, original)?;
+
+        if let Some(name) = trashed.file_name() {
+            let mut info_name = name.to_os_string();
+            info_name.push(".trashinfo");
+            let _ = std::fs::remove_file(self.info.join(info_name));
+        }
+        Ok(())
+    }
+}
+
+pub fn crosses_devices(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::EXDEV)
+}
+
+// the spec wants the path URL-escaped
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// "YYYY-MM-DDThh:mm:ss" in local time, as the spec asks
+fn local_timestamp() -> String {
+    let (year, month, day, hour, minute, second) = local_time(SystemTime::now());
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, hour, minute, second)
+}
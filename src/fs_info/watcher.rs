@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+// a burst of changes (an unpacking archive, a build) is one refresh, not hundreds
+static DEBOUNCE: Duration = Duration::from_millis(300);
+
+// tells when something else changed the listed dir, follows the listing from dir to dir
+pub struct DirWatcher {
+    watcher: RecommendedWatcher,
+    changes: Receiver<()>,
+    dir: Option<PathBuf>,
+    last_change: Option<Instant>, // unanswered change, waiting for the burst to end
+}
+
+impl DirWatcher {
+    pub fn new() -> notify::Result<Self> {
+        let (sender, changes) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // reads do not change the listing
+            if let Ok(event) = event {
+                if !event.kind.is_access() {
+                    let _ = sender.send(());
+                }
+            }
+        })?;
+        Ok(DirWatcher { watcher, changes, dir: None, last_change: None })
+    }
+
+    // only the dir itself, not what is below it
+    pub fn follow(&mut self, dir: &Path) {
+        if self.dir.as_deref() == Some(dir) {
+            return;
+        }
+        if let Some(old) = self.dir.take() {
+            let _ = self.watcher.unwatch(&old);
+        }
+        // an unwatchable dir (gone, inotify limit) just means no auto refresh there
+        if self.watcher.watch(dir, RecursiveMode::NonRecursive).is_ok() {
+            self.dir = Some(dir.to_path_buf());
+        }
+        // whatever was pending belonged to the old dir
+        self.changes.try_iter().for_each(drop);
+        self.last_change = None;
+    }
+
+    // true once things have been quiet for a moment after a change
+    pub fn settled(&mut self) -> bool {
+        if self.changes.try_iter().count() > 0 {
+            self.last_change = Some(Instant::now());
+        }
+        match self.last_change {
+            Some(at) if at.elapsed() >= DEBOUNCE => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
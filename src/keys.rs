@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use crate::config::config_dir;
+
+// everything a key can do outside of input mode and pickers
+#[derive(Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    // guide
+    Down,
+    Up,
+    Top,
+    Bottom,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    Parent,
+    Enter,
+    FollowSymlink,
+    Back,
+    Forward,
+    GoTo,
+    RecentDirs,
+    ToggleBookmark,
+    Bookmarks,
+
+    // selection
+    ToggleMark,
+    MarkAll,
+    UnmarkAll,
+    InvertMarks,
+    MarkGlob,
+    CopyRelativePath,
+    CopyPath,
+    CopyName,
+    Checksum,
+
+    // file operations
+    Copy,
+    Cut,
+    Paste,
+    PasteLink,
+    PasteFromHistory,
+    Delete,
+    EmptyDir,
+    Undo,
+    Redo,
+    Rename,
+    Edit,
+    RecentFiles,
+    Shell,
+    Subshell,
+    Command,
+    OpenWith,
+    OpenDefault,
+    Flatten,
+    Duplicate,
+    ToggleExecutable,
+    Touch,
+    Extract,
+    CreateArchive,
+    CopyToPane,
+    MoveToPane,
+
+    // create
+    NewFile,
+    NewDir,
+    NewDirEnter,
+
+    // view
+    ToggleExactSizes,
+    CycleSort,
+    ReverseSort,
+    ToggleOwner,
+    TogglePreview,
+    ToggleHex,
+    ScrollPreviewDown,
+    ScrollPreviewUp,
+    ToggleGit,
+    TogglePermissions,
+    ToggleModified,
+    SwitchPane,
+    TogglePanes,
+
+    // filter or search
+    ToggleHidden,
+    ToggleIgnored,
+    CycleTypeFilter,
+    Search,
+    SearchSubtree,
+    ToggleCase,
+    ToggleFuzzy,
+    ToggleSearchFilter,
+    NextMatch,
+    PrevMatch,
+    OwnerFilter,
+    ExtFilter,
+    FuzzyPick,
+    Escape,
+
+    // exit
+    Queue,
+    Quit,
+}
+
+type Key = (KeyCode, KeyModifiers);
+
+// key or key sequence ("g g") -> action; keys.toml only lists what differs from the defaults
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Action>,
+    pending: Vec<Key>, // start of a sequence, waiting for the rest
+}
+
+static DEFAULTS: &[(&str, Action)] = &[
+    ("j", Action::Down),
+    ("k", Action::Up),
+    ("down", Action::Down),
+    ("up", Action::Up),
+    ("g g", Action::Top),
+    ("G", Action::Bottom),
+    ("ctrl-f", Action::PageDown),
+    ("pagedown", Action::PageDown),
+    ("ctrl-b", Action::PageUp),
+    ("pageup", Action::PageUp),
+    ("ctrl-d", Action::HalfPageDown),
+    ("ctrl-u", Action::HalfPageUp),
+    ("h", Action::Parent),
+    ("l", Action::Enter),
+    ("enter", Action::Enter),
+    (">", Action::FollowSymlink),
+    ("alt-left", Action::Back),
+    ("alt-right", Action::Forward),
+    ("g o", Action::GoTo),
+    ("g r", Action::RecentDirs),
+    ("B", Action::ToggleBookmark),
+    ("'", Action::Bookmarks),
+    ("space", Action::ToggleMark),
+    ("A", Action::MarkAll),
+    ("U", Action::UnmarkAll),
+    ("I", Action::InvertMarks),
+    ("*", Action::MarkGlob),
+    ("alt-r", Action::CopyRelativePath),
+    ("y", Action::CopyPath),
+    ("Y", Action::CopyName),
+    ("alt-s", Action::Checksum),
+    ("c", Action::Copy),
+    ("x", Action::Cut),
+    ("v", Action::Paste),
+    ("V", Action::PasteLink),
+    ("P", Action::PasteFromHistory),
+    ("d", Action::Delete),
+    ("D", Action::EmptyDir),
+    ("u", Action::Undo),
+    ("ctrl-r", Action::Redo),
+    ("r", Action::Rename),
+    ("e", Action::Edit),
+    ("R", Action::RecentFiles),
+    (":", Action::Command),
+    ("!", Action::Shell),
+    ("$", Action::Subshell),
+    ("O", Action::OpenWith),
+    ("o", Action::OpenDefault),
+    ("F", Action::Flatten),
+    ("C", Action::Duplicate),
+    ("X", Action::ToggleExecutable),
+    ("alt-m", Action::Touch),
+    ("Z", Action::Extract),
+    ("alt-z", Action::CreateArchive),
+    ("f5", Action::CopyToPane),
+    ("f6", Action::MoveToPane),
+    ("n", Action::NewFile),
+    ("m", Action::NewDir),
+    ("M", Action::NewDirEnter),
+    ("b", Action::ToggleExactSizes),
+    ("s", Action::CycleSort),
+    ("S", Action::ReverseSort),
+    ("w", Action::ToggleOwner),
+    ("i", Action::TogglePreview),
+    ("H", Action::ToggleHex),
+    ("J", Action::ScrollPreviewDown),
+    ("K", Action::ScrollPreviewUp),
+    ("alt-g", Action::ToggleGit),
+    ("p", Action::TogglePermissions),
+    ("T", Action::ToggleModified),
+    ("tab", Action::SwitchPane),
+    ("|", Action::TogglePanes),
+    (".", Action::ToggleHidden),
+    ("alt-.", Action::ToggleIgnored),
+    ("alt-t", Action::CycleTypeFilter),
+    ("/", Action::Search),
+    // terminals disagree on what Ctrl-/ sends
+    ("ctrl-/", Action::SearchSubtree),
+    ("ctrl-_", Action::SearchSubtree),
+    ("ctrl-7", Action::SearchSubtree),
+    ("alt-c", Action::ToggleCase),
+    ("alt-f", Action::ToggleFuzzy),
+    ("alt-h", Action::ToggleSearchFilter),
+    // `n` is taken by new_file, keys.toml can move them there
+    ("ctrl-n", Action::NextMatch),
+    ("ctrl-p", Action::PrevMatch),
+    ("W", Action::OwnerFilter),
+    ("E", Action::ExtFilter),
+    ("f", Action::FuzzyPick),
+    ("esc", Action::Escape),
+    ("Q", Action::Queue),
+    ("q", Action::Quit),
+];
+
+impl Keymap {
+    // same rules as config.toml: missing file means defaults, bad entries are reported
+    // before the TUI takes the screen and leave the default binding of that key alone
+    pub fn load() -> Keymap {
+        let mut keymap = Keymap::default();
+        let path = match config_dir() {
+            Some(dir) => dir.join("keys.toml"),
+            None => return keymap,
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return keymap,
+        };
+
+        let entries: HashMap<String, String> = match toml::from_str(&text) {
+            Ok(entries) => entries,
+            Err(error) => {
+                eprintln!("npns: ignoring {}: {}", path.display(), error);
+                return keymap;
+            }
+        };
+        for (name, action) in entries {
+            let key = match parse_sequence(&name) {
+                Some(key) => key,
+                None => {
+                    eprintln!("npns: {}: unknown key \"{}\"", path.display(), name);
+                    continue;
+                }
+            };
+            // "" frees a key
+            if action.is_empty() {
+                keymap.bindings.remove(&key);
+                continue;
+            }
+            match parse_action(&action) {
+                Some(action) => {
+                    keymap.bindings.insert(key, action);
+                }
+                None => eprintln!("npns: {}: unknown action \"{}\" for \"{}\"", path.display(), action, name),
+            }
+        }
+        keymap
+    }
+
+    // None also while a sequence is still open; a key that ends none starts over on its own
+    pub fn action(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending.push(normalize(key.code, key.modifiers));
+        if let Some(action) = self.bindings.get(&self.pending) {
+            self.pending.clear();
+            return Some(*action);
+        }
+        if self.is_prefix(&self.pending) {
+            return None;
+        }
+
+        let started_over = self.pending.len() > 1;
+        self.pending.clear();
+        if started_over {
+            return self.action(key);
+        }
+        None
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn is_prefix(&self, keys: &[Key]) -> bool {
+        self.bindings.keys().any(|sequence| sequence.len() > keys.len() && sequence.starts_with(keys))
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULTS
+            .iter()
+            .map(|(name, action)| (parse_sequence(name).expect("bad default key"), *action))
+            .collect();
+        Keymap { bindings, pending: Vec::new() }
+    }
+}
+
+// the snake_case name used in keys.toml and as a ":" command
+pub fn parse_action(name: &str) -> Option<Action> {
+    toml::Value::String(name.to_string()).try_into().ok()
+}
+
+// keys separated by spaces are pressed one after the other: "g g"
+fn parse_sequence(name: &str) -> Option<Vec<Key>> {
+    let keys: Vec<Key> = name.split_whitespace().map(parse_key).collect::<Option<_>>()?;
+    if keys.is_empty() {
+        // a lone space is the space bar, not an empty sequence
+        return if name.is_empty() { None } else { Some(vec![(KeyCode::Char(' '), KeyModifiers::NONE)]) };
+    }
+    Some(keys)
+}
+
+// "j", "J", "ctrl-r", "alt-left", "enter", "f5", ...
+fn parse_key(name: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop {
+        if let Some(after) = rest.strip_prefix("ctrl-") {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("alt-") {
+            modifiers |= KeyModifiers::ALT;
+            rest = after;
+        } else {
+            break;
+        }
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" => KeyCode::Delete,
+            other => match other.strip_prefix('f').and_then(|number| number.parse().ok()) {
+                Some(number @ 1..=12) => KeyCode::F(number),
+                _ => return None,
+            },
+        },
+    };
+    Some(normalize(code, modifiers))
+}
+
+// shift is already in the char ("J"), only ctrl and alt tell keys apart
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> Key {
+    (code, modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT))
+}
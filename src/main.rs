@@ -1,41 +1,120 @@
 mod fs_info;
 mod app;
+mod config;
+mod keys;
+mod state;
+mod theme;
+mod tui;
 
-use anyhow::Result;
-use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Result};
 use crate::app::App;
+use crate::config::Config;
+use crate::keys::Keymap;
+use crate::theme::Theme;
 
-use crossterm::{
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use ratatui::{
-    backend::CrosstermBackend,
-    Terminal,
-};
 
+static USAGE: &str = "\
+usage: npns [options] [path]
+
+  path               dir to start in, or a file to start next to
+  --restore          start in the dir the last run quit in
+  --choosedir <file> write the final dir to <file> on quit, for `cd` wrappers
+  -h, --help         show this and exit
+  -V, --version      show the version and exit";
+
+#[derive(Default)]
+struct Args {
+    path: Option<PathBuf>,
+    restore: bool,
+    choose_dir: Option<PathBuf>, // gets the final dir, for a shell wrapper to cd into
+}
 
 fn main() -> Result<()> {
-    let start_dir = std::env::current_dir()?;
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut app = App::new(start_dir)?;
-    let res = app.run(&mut terminal);
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen
-    )?;
-    terminal.show_cursor()?;
-
-    if let Err(err) = res {
-        println!("{err:?}");
+    // bad arguments are reported before the terminal is taken over
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => return Ok(()), // --help or --version
+        Err(error) => {
+            eprintln!("npns: {}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let config = Config::load();
+    // (dir, file to put the cursor on)
+    let (start_dir, focus) = match args.path {
+        Some(path) => match start_at(&path) {
+            Ok(start) => start,
+            Err(error) => {
+                eprintln!("npns: {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        },
+        // a saved dir that is gone by now falls back to where we were started
+        None => match state::load_session().last_dir {
+            Some(dir) if (args.restore || config.restore_dir) && dir.is_dir() => (dir, None),
+            _ => (std::env::current_dir()?, None),
+        },
+    };
+    let keymap = Keymap::load();
+    let theme = Theme::load();
+    let mut terminal = tui::setup_terminal(config.mouse)?;
+
+    let res = App::new(start_dir, config, keymap, theme).and_then(|mut app| {
+        if let Some(name) = focus {
+            app.select(&name);
+        }
+        app.run(&mut terminal).map(|()| app)
+    });
+
+    tui::restore_terminal(&mut terminal)?;
+
+    match res {
+        Ok(app) => {
+            if let Some(file) = args.choose_dir {
+                std::fs::write(file, app.current_dir().as_os_str().as_bytes())?;
+            }
+        }
+        Err(err) => println!("{err:?}"),
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// None when there is nothing left to do
+fn parse_args() -> Result<Option<Args>> {
+    let mut parsed = Args::default();
+    // paths are taken as they are, not all of them are valid UTF-8
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("-h" | "--help") => {
+                println!("{}", USAGE);
+                return Ok(None);
+            }
+            Some("-V" | "--version") => {
+                println!("npns {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            }
+            Some("--restore") => parsed.restore = true,
+            Some("--choosedir") => parsed.choose_dir = Some(args.next().ok_or_else(|| anyhow!("--choosedir needs a file"))?.into()),
+            _ if arg.as_bytes().starts_with(b"-") => bail!("unknown option: {}", arg.to_string_lossy()),
+            _ if parsed.path.is_some() => bail!("more than one path: {}", arg.to_string_lossy()),
+            _ => parsed.path = Some(arg.into()),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+// a dir is entered, a file opens its dir with the cursor on it
+fn start_at(path: &Path) -> Result<(PathBuf, Option<String>)> {
+    let path = path.canonicalize()?;
+    if path.is_dir() {
+        return Ok((path, None));
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => Ok((dir.to_path_buf(), Some(name.to_string_lossy().into_owned()))),
+        _ => bail!("not a dir or file"),
+    }
+}
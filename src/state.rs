@@ -0,0 +1,71 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::config::config_dir;
+use crate::fs_info::file_system_info::SortMode;
+
+static SESSION: &str = "session.toml";
+
+// view settings saved on quit and restored on the next start
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    pub show_hidden: bool,
+    pub sort_mode: SortMode,
+    pub sort_reversed: bool,
+    pub show_preview: bool,
+    pub last_dir: Option<PathBuf>, // where `--restore` starts
+}
+
+// missing or broken means defaults, nobody needs to hear about it
+pub fn load_session() -> Session {
+    config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join(SESSION)).ok())
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_session(session: &Session) -> Result<()> {
+    let dir = config_dir().ok_or_else(|| anyhow!("No config dir"))?;
+    std::fs::create_dir_all(&dir)?;
+    write_atomic(&dir.join(SESSION), &toml::to_string(session)?)
+}
+
+// a crash halfway through leaves the old file, not half of the new one
+fn write_atomic(path: &Path, text: &str) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, text)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+// plain lists of paths kept next to the config, one per line
+
+pub fn load_paths(file_name: &str) -> Vec<PathBuf> {
+    let path = match config_dir() {
+        Some(dir) => dir.join(file_name),
+        None => return Vec::new(),
+    };
+
+    match std::fs::read_to_string(path) {
+        Ok(text) => text.lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn save_paths<'a>(file_name: &str, paths: impl IntoIterator<Item = &'a PathBuf>) -> Result<()> {
+    let dir = config_dir().ok_or_else(|| anyhow!("No config dir"))?;
+    std::fs::create_dir_all(&dir)?;
+
+    let mut text = String::new();
+    for path in paths {
+        text.push_str(&path.to_string_lossy());
+        text.push('\n');
+    }
+    std::fs::write(dir.join(file_name), text)?;
+    Ok(())
+}
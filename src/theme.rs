@@ -0,0 +1,85 @@
+use std::str::FromStr;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+use crate::config::config_dir;
+
+// colors are names ("cyan", "lightred"), "#rrggbb", "r,g,b" or a 256-color index ("208")
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    #[serde(deserialize_with = "color")]
+    pub marked: Color, // marked rows, bold on top
+    #[serde(deserialize_with = "color")]
+    pub directory: Color,
+    #[serde(deserialize_with = "color")]
+    pub symlink: Color, // broken ones take `error`
+    #[serde(deserialize_with = "color")]
+    pub executable: Color,
+    #[serde(deserialize_with = "color")]
+    pub archive: Color, // .zip, .tar.gz, .deb, ...
+    #[serde(deserialize_with = "color")]
+    pub error: Color, // error status and error messages in the panes
+    #[serde(deserialize_with = "color")]
+    pub ready: Color,
+    #[serde(deserialize_with = "color")]
+    pub input: Color, // input prompts and the "waiting for input" status
+    #[serde(deserialize_with = "color")]
+    pub others: Color, // any other status message
+    #[serde(deserialize_with = "color")]
+    pub confirm: Color, // y/N and "yes" prompts
+    #[serde(deserialize_with = "color")]
+    pub search: Color, // search prompts
+    #[serde(deserialize_with = "color")]
+    pub search_match: Color, // the part of a name the search matched, bold on top
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            marked: Color::Cyan,
+            directory: Color::Reset,
+            symlink: Color::LightCyan,
+            executable: Color::Green,
+            archive: Color::LightRed,
+            error: Color::Red,
+            ready: Color::Green,
+            input: Color::Yellow,
+            others: Color::White,
+            confirm: Color::Magenta,
+            search: Color::Gray,
+            search_match: Color::Yellow,
+        }
+    }
+}
+
+impl Theme {
+    // same rules as config.toml, a broken file is reported and the whole palette stays default
+    pub fn load() -> Theme {
+        let path = match config_dir() {
+            Some(dir) => dir.join("theme.toml"),
+            None => return Theme::default(),
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|error| {
+                eprintln!("npns: ignoring {}: {}", path.display(), error);
+                Theme::default()
+            }),
+            Err(_) => Theme::default(),
+        }
+    }
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_color(&text).ok_or_else(|| serde::de::Error::custom(format!("unknown color \"{}\"", text)))
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    if let [r, g, b] = parts[..] {
+        return Some(Color::Rgb(r.parse().ok()?, g.parse().ok()?, b.parse().ok()?));
+    }
+    // names, "#rrggbb" and indices are what ratatui already understands
+    Color::from_str(text.trim()).ok()
+}
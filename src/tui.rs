@@ -0,0 +1,78 @@
+use anyhow::Result;
+use std::io::{self, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    Terminal,
+};
+use signal_hook::consts::SIGTSTP;
+use signal_hook::low_level::raise;
+
+pub type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+// mouse reporting is switched on and off along with the alternate screen
+static MOUSE: AtomicBool = AtomicBool::new(false);
+
+pub fn setup_terminal(mouse: bool) -> Result<Tui> {
+    MOUSE.store(mouse, Ordering::Relaxed);
+    install_panic_hook();
+    enter_screen()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    Ok(Terminal::new(backend)?)
+}
+
+pub fn restore_terminal(terminal: &mut Tui) -> Result<()> {
+    disable_raw_mode()?;
+    if MOUSE.load(Ordering::Relaxed) {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen
+    )?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+// raw mode swallows ^Z, so stop ourselves the way the tty would have
+pub fn suspend_terminal(terminal: &mut Tui) -> Result<()> {
+    restore_terminal(terminal)?;
+    raise(SIGTSTP)?;
+    Ok(())
+}
+
+// after SIGCONT the shell has reset the tty, set it up again and force a full redraw
+pub fn resume_terminal(terminal: &mut Tui) -> Result<()> {
+    enter_screen()?;
+    terminal.clear()?;
+    Ok(())
+}
+
+// a panic would otherwise leave the shell in raw mode on the alternate screen
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        if MOUSE.load(Ordering::Relaxed) {
+            let _ = execute!(io::stdout(), DisableMouseCapture);
+        }
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        default_hook(info);
+    }));
+}
+
+fn enter_screen() -> Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if MOUSE.load(Ordering::Relaxed) {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    Ok(())
+}